@@ -0,0 +1,226 @@
+// Alarms set for a time in the zone of one of the clocks. They are kept in a json file next to the configuration
+// and ring while the dashboard runs: once, every day or on weekdays only.
+use chrono::{DateTime, Datelike, Days, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{fs::{read_to_string, write}, io, path::{Path, PathBuf}};
+
+pub const ALARMS_FILE: &str = "alarms.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    #[default]
+    Once,
+    Daily,
+    Weekdays,
+}
+
+impl Repeat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Repeat::Once => "once",
+            Repeat::Daily => "daily",
+            Repeat::Weekdays => "weekdays",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Repeat::Once => Repeat::Daily,
+            Repeat::Daily => Repeat::Weekdays,
+            Repeat::Weekdays => Repeat::Once,
+        }
+    }
+
+    fn allows(&self, day: Weekday) -> bool {
+        match self {
+            Repeat::Once | Repeat::Daily => true,
+            Repeat::Weekdays => !matches!(day, Weekday::Sat | Weekday::Sun),
+        }
+    }
+}
+
+// The time and the zone are kept as written in the file, like "07:30" and "Asia/Tokyo", so that the file stays easy
+// to edit by hand. An alarm which can't be understood never rings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alarm {
+    pub time: String,
+    pub zone: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub repeat: Repeat,
+}
+
+impl Alarm {
+    pub fn new(time: &str, zone: Tz, label: &str, repeat: Repeat) -> Result<Self, String> {
+        let time = parse_time(time)?;
+        Ok(Self { time: time.format("%H:%M").to_string(), zone: zone.name().to_string(), label: label.trim().to_string(), repeat })
+    }
+
+    fn tz(&self) -> Option<Tz> {
+        self.zone.parse().ok()
+    }
+
+    // First time the alarm rings strictly after the given one. A time skipped by the change to the summer time
+    // rings an hour later.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let zone = self.tz()?;
+        let time = parse_time(&self.time).ok()?;
+        let today = after.with_timezone(&zone).date_naive();
+        (0..8).filter_map(|days| today.checked_add_days(Days::new(days)))
+            .filter(|date| self.repeat.allows(date.weekday()))
+            .filter_map(|date| {
+                let local = date.and_time(time);
+                zone.from_local_datetime(&local).earliest()
+                    .or_else(|| zone.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+            })
+            .map(|time| time.with_timezone(&Utc))
+            .find(|time| *time > after)
+    }
+}
+
+// "7:30" or "07:30"
+fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M").map_err(|_| format!("{} is not a time such as 07:30", input.trim()))
+}
+
+pub struct Alarms {
+    path: PathBuf,
+    alarms: Vec<Alarm>,
+    checked_at: DateTime<Utc>, // The alarms due before were either rung or set while the dashboard was closed
+}
+
+impl Alarms {
+    // A missing file means that no alarm was set yet, while an invalid one is reported
+    pub fn load(path: &Path, now: DateTime<Utc>) -> io::Result<Self> {
+        let alarms = match read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {error}", path.display())))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Self { path: path.to_path_buf(), alarms, checked_at: now })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        write(&self.path, serde_json::to_string_pretty(&self.alarms).map_err(io::Error::other)?)
+    }
+
+    pub fn list(&self) -> &[Alarm] {
+        &self.alarms
+    }
+
+    pub fn add(&mut self, alarm: Alarm) -> io::Result<()> {
+        self.alarms.push(alarm);
+        self.save()
+    }
+
+    pub fn remove(&mut self, index: usize) -> io::Result<()> {
+        if index < self.alarms.len() {
+            self.alarms.remove(index);
+        }
+        self.save()
+    }
+
+    // The alarms which rang since the last check. Those which only ring once are then removed. They ring even when
+    // the file can't be saved without them, the error being returned next to them.
+    pub fn due(&mut self, now: DateTime<Utc>) -> (Vec<Alarm>, io::Result<()>) {
+        let checked_at = self.checked_at;
+        self.checked_at = now;
+        let is_due = |alarm: &Alarm| alarm.next_after(checked_at).is_some_and(|time| time <= now);
+        let due: Vec<Alarm> = self.alarms.iter().filter(|alarm| is_due(alarm)).cloned().collect();
+        let mut saved = Ok(());
+        if due.iter().any(|alarm| alarm.repeat == Repeat::Once) {
+            self.alarms.retain(|alarm| alarm.repeat != Repeat::Once || !is_due(alarm));
+            saved = self.save();
+        }
+        (due, saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn alarm(time: &str, zone: &str, repeat: Repeat) -> Alarm {
+        Alarm::new(time, zone.parse().unwrap(), "test", repeat).unwrap()
+    }
+
+    fn alarms(name: &str, list: Vec<Alarm>, now: DateTime<Utc>) -> Alarms {
+        let path = temp_dir().join(format!("alarms_{name}_{}.json", std::process::id()));
+        Alarms { path, alarms: list, checked_at: now }
+    }
+
+    #[test]
+    fn rings_at_the_time_of_its_zone() {
+        // 9:00 in Tokyo is midnight UTC
+        let alarm = alarm("9:00", "Asia/Tokyo", Repeat::Daily);
+        assert_eq!(alarm.time, "09:00");
+        assert_eq!(alarm.next_after(utc("2025-01-20T12:00:00Z")), Some(utc("2025-01-21T00:00:00Z")));
+        // New York is on the summer time in July
+        let alarm = self::alarm("07:30", "America/New_York", Repeat::Daily);
+        assert_eq!(alarm.next_after(utc("2025-07-01T00:00:00Z")), Some(utc("2025-07-01T11:30:00Z")));
+    }
+
+    #[test]
+    fn weekday_alarms_skip_the_weekend() {
+        let alarm = alarm("08:00", "Europe/London", Repeat::Weekdays);
+        // Friday 2025-01-24 after 8:00, the next one is on Monday
+        assert_eq!(alarm.next_after(utc("2025-01-24T09:00:00Z")), Some(utc("2025-01-27T08:00:00Z")));
+    }
+
+    #[test]
+    fn a_time_skipped_by_the_summer_time_rings_an_hour_later() {
+        // Clocks go from 1:00 to 2:00 in London on 2025-03-30
+        let alarm = alarm("01:30", "Europe/London", Repeat::Daily);
+        assert_eq!(alarm.next_after(utc("2025-03-29T12:00:00Z")), Some(utc("2025-03-30T01:30:00Z")));
+    }
+
+    #[test]
+    fn rings_once_per_occurrence() {
+        let mut alarms = alarms("daily", vec![alarm("08:00", "Europe/London", Repeat::Daily)], utc("2025-01-20T07:59:00Z"));
+        assert!(alarms.due(utc("2025-01-20T07:59:30Z")).0.is_empty());
+        assert_eq!(alarms.due(utc("2025-01-20T08:00:00Z")).0.len(), 1);
+        assert!(alarms.due(utc("2025-01-20T08:00:01Z")).0.is_empty());
+        assert_eq!(alarms.list().len(), 1);
+    }
+
+    #[test]
+    fn one_time_alarms_are_removed_once_rung() {
+        let mut alarms = alarms("once", vec![
+            alarm("08:00", "Europe/London", Repeat::Once),
+            alarm("09:00", "Europe/London", Repeat::Once),
+        ], utc("2025-01-20T07:00:00Z"));
+        let (due, saved) = alarms.due(utc("2025-01-20T08:30:00Z"));
+        assert!(saved.is_ok());
+        assert_eq!(due.iter().map(|alarm| alarm.time.as_str()).collect::<Vec<_>>(), ["08:00"]);
+        assert_eq!(alarms.list().iter().map(|alarm| alarm.time.as_str()).collect::<Vec<_>>(), ["09:00"]);
+        // They are saved without the one which rang
+        let saved = Alarms::load(&alarms.path, utc("2025-01-20T08:30:00Z")).unwrap();
+        assert_eq!(saved.list(), alarms.list());
+        std::fs::remove_file(&alarms.path).unwrap();
+    }
+
+    #[test]
+    fn alarms_ring_even_when_they_cannot_be_saved() {
+        let mut alarms = alarms("unsaved", vec![alarm("08:00", "Europe/London", Repeat::Once)], utc("2025-01-20T07:00:00Z"));
+        alarms.path = temp_dir().join(format!("no_such_dir_{}", std::process::id())).join("alarms.json");
+        let (due, saved) = alarms.due(utc("2025-01-20T08:30:00Z"));
+        assert_eq!(due.len(), 1);
+        assert!(saved.is_err());
+        assert!(alarms.list().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert!(Alarm::new("25:00", Tz::UTC, "", Repeat::Once).is_err());
+        assert!(Alarm::new("soon", Tz::UTC, "", Repeat::Once).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+// Popup listing the alarms, with a small form to add one, and the alert shown when alarms ring. The zones offered
+// for a new alarm are the ones of the clocks.
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Constraint,
+    style::{Style, Stylize},
+    widgets::{Clear, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use std::io;
+
+use crate::{alarm::{Alarm, Alarms, Repeat}, block_with, clock::Clock, popup_area};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Time,
+    Zone,
+    Repeat,
+    Label,
+}
+
+impl Field {
+    fn next(&self) -> Self {
+        match self {
+            Field::Time => Field::Zone,
+            Field::Zone => Field::Repeat,
+            Field::Repeat => Field::Label,
+            Field::Label => Field::Time,
+        }
+    }
+}
+
+struct AlarmForm {
+    field: Field,
+    time: String,
+    zone: usize, // index of the clock
+    repeat: Repeat,
+    label: String,
+    error: Option<String>,
+}
+
+pub struct AlarmPanel {
+    table_state: TableState,
+    form: Option<AlarmForm>,
+    error: Option<String>, // A failure to save the alarms is shown rather than closing the dashboard
+}
+
+// The name of the clock showing the zone, or the zone itself
+fn zone_name<'a>(zone: &'a str, clocks: &'a [Clock]) -> &'a str {
+    clocks.iter().find(|clock| clock.zone().name() == zone).map_or(zone, |clock| clock.name.as_str())
+}
+
+// What is shown while alarms ring, until Enter is pressed, with the error if the alarms rung couldn't be saved
+pub fn render_alert(f: &mut Frame, ringing: &[Alarm], clocks: &[Clock], error: Option<&str>) {
+    let mut lines: Vec<String> = ringing.iter()
+        .map(|alarm| format!("{} {}  {}", alarm.time, zone_name(&alarm.zone, clocks), alarm.label))
+        .collect();
+    lines.extend(error.map(String::from));
+    lines.push("Press Enter to dismiss".to_string());
+    let area = popup_area(f.area(), 50, lines.len() as u16 + 2);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines.join("\n")).block(block_with("Alarm!")).black().on_red(), area);
+}
+
+// What is shown when the alarms file can't be written
+pub fn save_error(error: io::Error) -> String {
+    format!("The alarms could not be saved: {error}")
+}
+
+impl AlarmPanel {
+    pub fn new() -> Self {
+        Self { table_state: TableState::default().with_selected(Some(0)), form: None, error: None }
+    }
+
+    // Returns false once the panel is closed, by Esc or by the key bound to the alarms when `closes` is set
+    pub fn handle_key(&mut self, code: KeyCode, closes: bool, alarms: &mut Alarms, clocks: &[Clock]) -> bool {
+        let Some(form) = &mut self.form else {
+            match code {
+                KeyCode::Esc => return false,
+                KeyCode::Up => self.table_state.select_previous(),
+                KeyCode::Down => self.table_state.select_next(),
+                KeyCode::Char('n') if !clocks.is_empty() => {
+                    self.form = Some(AlarmForm {
+                        field: Field::Time,
+                        time: String::new(),
+                        zone: 0,
+                        repeat: Repeat::Once,
+                        label: String::new(),
+                        error: None,
+                    });
+                },
+                KeyCode::Char('d') | KeyCode::Delete => {
+                    if let Some(index) = self.table_state.selected() {
+                        self.error = alarms.remove(index).err().map(save_error);
+                    }
+                },
+                _ if closes => return false,
+                _ => {},
+            }
+            return true;
+        };
+        match (code, form.field) {
+            (KeyCode::Esc, _) => self.form = None,
+            (KeyCode::Tab, _) => form.field = form.field.next(),
+            (KeyCode::Enter, _) => {
+                match Alarm::new(&form.time, clocks[form.zone].zone(), &form.label, form.repeat) {
+                    Ok(alarm) => {
+                        self.error = alarms.add(alarm).err().map(save_error);
+                        self.table_state.select(Some(alarms.list().len() - 1));
+                        self.form = None;
+                    },
+                    Err(error) => form.error = Some(error),
+                }
+            },
+            (KeyCode::Right | KeyCode::Char(' '), Field::Zone) => form.zone = (form.zone + 1) % clocks.len(),
+            (KeyCode::Left, Field::Zone) => form.zone = (form.zone + clocks.len() - 1) % clocks.len(),
+            (KeyCode::Right | KeyCode::Left | KeyCode::Char(' '), Field::Repeat) => form.repeat = form.repeat.next(),
+            (KeyCode::Char(c), Field::Time) if c.is_ascii_digit() || c == ':' => form.time.push(c),
+            (KeyCode::Backspace, Field::Time) => {form.time.pop();},
+            (KeyCode::Char(c), Field::Label) => form.label.push(c),
+            (KeyCode::Backspace, Field::Label) => {form.label.pop();},
+            _ => {},
+        }
+        true
+    }
+
+    pub fn render(&mut self, f: &mut Frame, alarms: &Alarms, clocks: &[Clock]) {
+        let area = popup_area(f.area(), 70, 16);
+        f.render_widget(Clear, area);
+        if let Some(form) = &self.form {
+            let field = |name: &str, field: Field, value: &str| {
+                let line = format!("{name:<7}{value}");
+                if form.field == field {format!("> {line}")} else {format!("  {line}")}
+            };
+            let mut lines = vec![
+                field("Time", Field::Time, &form.time),
+                field("Zone", Field::Zone, &format!("< {} >", clocks[form.zone].name)),
+                field("Repeat", Field::Repeat, &format!("< {} >", form.repeat.name())),
+                field("Label", Field::Label, &form.label),
+            ];
+            if let Some(error) = &form.error {
+                lines.push(String::new());
+                lines.push(error.clone());
+            }
+            let title = "New alarm (Tab: next field, Left/Right: change, Enter: save, Esc: cancel)";
+            f.render_widget(Paragraph::new(lines.join("\n")).block(block_with(title)), area);
+            return;
+        }
+        let rows: Vec<Row> = alarms.list().iter()
+            .map(|alarm| Row::new(vec![
+                alarm.time.clone(),
+                zone_name(&alarm.zone, clocks).to_string(),
+                alarm.repeat.name().to_string(),
+                alarm.label.clone(),
+            ]))
+            .collect();
+        let mut table = Table::new(rows, [Constraint::Length(6), Constraint::Length(16), Constraint::Length(9), Constraint::Min(0)])
+            .header(Row::new(vec!["Time", "Zone", "Repeat", "Label"]).bold())
+            .row_highlight_style(Style::default().reversed())
+            .block(block_with("Alarms (n: new, d: delete, Up/Down: select, Esc: close)"));
+        if let Some(error) = &self.error {
+            table = table.footer(Row::new(vec![String::new(), String::new(), String::new(), error.clone()]).red());
+        }
+        f.render_stateful_widget(table, area, &mut self.table_state);
+    }
+}
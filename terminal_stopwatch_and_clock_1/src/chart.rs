@@ -0,0 +1,147 @@
+// Chart of the hourly forecast. Several series can be overlaid, like the temperature and the apparent temperature
+// of a location or the same variable for every location, with a vertical line marking the current time.
+use chrono::{DateTime, Local, Utc};
+use ratatui::{
+    style::{Color, Style, Stylize},
+    symbols,
+    widgets::{Axis, Block, Chart, Dataset, GraphType},
+};
+
+const X_TICKS: usize = 5;
+const Y_TICKS: usize = 5;
+// The yellow is kept for the current time
+const COLORS: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Green, Color::LightBlue, Color::LightRed, Color::White];
+
+// What the chart shows besides the selected variable of the selected location
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlay {
+    None,
+    FeelsLike, // temperature and apparent temperature of the selected location
+    Locations, // the selected variable for every location
+}
+
+impl Overlay {
+    pub fn next(&self) -> Self {
+        match self {
+            Overlay::None => Overlay::FeelsLike,
+            Overlay::FeelsLike => Overlay::Locations,
+            Overlay::Locations => Overlay::None,
+        }
+    }
+}
+
+// Points of one line of the chart, with x in hours since the Unix epoch
+pub struct Series {
+    pub name: String,
+    pub data: Vec<(f64, f64)>,
+}
+
+// Count values evenly spaced from min to max, both included
+pub fn ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![min],
+        _ => (0..count).map(|i| min + (max - min) * i as f64 / (count - 1) as f64).collect(),
+    }
+}
+
+// Smallest and largest values of every series. A flat line gets some room above and below it.
+fn bounds(series: &[Series], value: impl Fn(&(f64, f64)) -> f64) -> Option<(f64, f64)> {
+    let (min, max) = series.iter()
+        .flat_map(|series| series.data.iter().map(&value))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    match (min, max) {
+        _ if min > max => None,
+        _ if min == max => Some((min - 1.0, max + 1.0)),
+        _ => Some((min, max)),
+    }
+}
+
+// The hours are shown in the local time of the computer, like the marker of the current time
+fn time_label(hours: f64) -> String {
+    DateTime::from_timestamp((hours * 3600.0).round() as i64, 0)
+        .map(|time| time.with_timezone(&Local).format("%a %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// The marker and the bounds are returned apart from the chart, which only borrows its data
+pub fn now_marker(series: &[Series]) -> Vec<(f64, f64)> {
+    let now = Utc::now().timestamp() as f64 / 3600.0;
+    match (bounds(series, |(x, _)| *x), bounds(series, |(_, y)| *y)) {
+        (Some((x_min, x_max)), Some((y_min, y_max))) if (x_min..=x_max).contains(&now) => vec![(now, y_min), (now, y_max)],
+        _ => Vec::new(),
+    }
+}
+
+pub fn forecast_chart<'a>(series: &'a [Series], now: &'a [(f64, f64)], y_title: String, block: Block<'a>) -> Chart<'a> {
+    let (x_min, x_max) = bounds(series, |(x, _)| *x).unwrap_or((0.0, 1.0));
+    let (y_min, y_max) = bounds(series, |(_, y)| *y).unwrap_or((0.0, 1.0));
+    let mut datasets: Vec<Dataset> = series.iter().zip(COLORS.iter().cycle())
+        .map(|(series, color)| Dataset::default()
+            .name(series.name.clone())
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(*color))
+            .data(&series.data))
+        .collect();
+    if !now.is_empty() {
+        datasets.push(Dataset::default()
+            .name("now")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().yellow())
+            .data(now));
+    }
+    let x_axis = Axis::default()
+        .title("Time".red())
+        .style(Style::default().white())
+        .bounds([x_min, x_max])
+        .labels(ticks(x_min, x_max, X_TICKS).into_iter().map(time_label));
+    let y_axis = Axis::default()
+        .title(y_title.green())
+        .style(Style::default().white())
+        .bounds([y_min, y_max])
+        .labels(ticks(y_min, y_max, Y_TICKS).into_iter().map(|y| format!("{y:.1}")));
+    Chart::new(datasets)
+        .block(block)
+        .x_axis(x_axis)
+        .y_axis(y_axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(data: Vec<(f64, f64)>) -> Series {
+        Series { name: "test".to_string(), data }
+    }
+
+    #[test]
+    fn ticks_are_evenly_spaced() {
+        assert_eq!(ticks(0.0, 10.0, 5), vec![0.0, 2.5, 5.0, 7.5, 10.0]);
+        assert_eq!(ticks(-3.0, 3.0, 3), vec![-3.0, 0.0, 3.0]);
+        assert_eq!(ticks(1.0, 2.0, 1), vec![1.0]);
+        assert!(ticks(1.0, 2.0, 0).is_empty());
+    }
+
+    #[test]
+    fn bounds_cover_every_series() {
+        let series = [series(vec![(0.0, 4.0), (1.0, 6.0)]), series(vec![(2.0, -1.0)])];
+        assert_eq!(bounds(&series, |(x, _)| *x), Some((0.0, 2.0)));
+        assert_eq!(bounds(&series, |(_, y)| *y), Some((-1.0, 6.0)));
+        // A flat line is not drawn on the edge of the chart
+        assert_eq!(bounds(&series[1..], |(_, y)| *y), Some((-2.0, 0.0)));
+        assert_eq!(bounds(&[], |(x, _)| *x), None);
+    }
+
+    #[test]
+    fn marks_now_only_within_the_forecast() {
+        let now = Utc::now().timestamp() as f64 / 3600.0;
+        let current = [series(vec![(now - 1.0, 4.0), (now + 1.0, 8.0)])];
+        let marker = now_marker(&current);
+        assert_eq!(marker.len(), 2);
+        assert!((marker[0].0 - now).abs() < 0.1);
+        assert_eq!((marker[0].1, marker[1].1), (4.0, 8.0));
+        assert!(now_marker(&[series(vec![(now + 1.0, 4.0), (now + 2.0, 8.0)])]).is_empty());
+    }
+}
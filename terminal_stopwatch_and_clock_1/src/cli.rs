@@ -0,0 +1,128 @@
+// Command line interface. Without any option, the clocks listed in the configuration file are displayed.
+use clap::Parser;
+use std::{path::PathBuf, time::Duration};
+
+use crate::{alarm::ALARMS_FILE, clock::Clock, config::{LocationConfig, CONFIG_FILE}, headless::OutputFormat, keymap::KEYMAP_FILE,
+    session_log::SESSIONS_FILE, stopwatch::Precision, weather::CACHE_FILE};
+
+#[derive(Debug, Parser)]
+#[command(name = "terminal_stopwatch_and_clock", about = "Stopwatch, world clocks and weather forecast in the terminal")]
+pub struct Cli {
+    /// Configuration file (json)
+    #[arg(long, default_value = CONFIG_FILE)]
+    pub config: PathBuf,
+    /// Clock to display: an IANA time zone such as "Asia/Kolkata", optionally named ("Delhi=Asia/Kolkata").
+    /// Can be repeated. Replaces the clocks of the configuration file.
+    #[arg(long = "clock", value_parser = Clock::parse)]
+    pub clocks: Vec<Clock>,
+    /// Location of a weather forecast: a place name ("Paris") or coordinates ("48.85,2.35"), optionally named
+    /// ("Home=48.85,2.35"). Can be repeated. Replaces the locations of the configuration file.
+    #[arg(long = "location", value_parser = LocationConfig::parse)]
+    pub locations: Vec<LocationConfig>,
+    /// File the alarms are kept in (json)
+    #[arg(long, default_value = ALARMS_FILE)]
+    pub alarms: PathBuf,
+    /// Key bindings file (json)
+    #[arg(long, default_value = KEYMAP_FILE)]
+    pub keymap: PathBuf,
+    /// File the laps of the stopwatch are exported to when leaving, as CSV
+    #[arg(long, default_value = "laps.csv")]
+    pub laps_csv: PathBuf,
+    /// File every stopped timing of the stopwatch is logged to, with its label (json lines)
+    #[arg(long, default_value = SESSIONS_FILE)]
+    pub sessions: PathBuf,
+    /// File the time report is exported to, as CSV
+    #[arg(long, default_value = "report.csv")]
+    pub report_csv: PathBuf,
+    /// Duration of the countdown timer, like "90", "90s", "25m" or "1h30m". Defaults to the configuration file.
+    #[arg(long, value_parser = parse_duration)]
+    pub countdown: Option<Duration>,
+    /// Digits of the stopwatch after the seconds. Defaults to the configuration file.
+    #[arg(long, value_enum)]
+    pub precision: Option<Precision>,
+    /// Minutes between two refreshes of the weather forecast. Defaults to the configuration file.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub refresh_minutes: Option<u64>,
+    /// Base URL of the open-meteo forecast API, for a mirror or a local mock server
+    #[arg(long)]
+    pub forecast_url: Option<String>,
+    /// Base URL of the open-meteo geocoding API
+    #[arg(long)]
+    pub geocoding_url: Option<String>,
+    /// File the last forecasts are kept in, to be shown while offline
+    #[arg(long, default_value = CACHE_FILE)]
+    pub weather_cache: PathBuf,
+    /// Print the clocks and the forecast instead of starting the dashboard
+    #[arg(long, value_enum)]
+    pub output: Option<OutputFormat>,
+    /// Print them again on this interval, like "10s" or "5m". Zero is refused like for the countdown, as the
+    /// reports would be printed in a busy loop.
+    #[arg(long, value_parser = parse_duration, requires = "output")]
+    pub watch: Option<Duration>,
+    /// Hours of forecast printed from the current one. Defaults to the whole forecast.
+    #[arg(long, requires = "output")]
+    pub hours: Option<usize>,
+}
+
+// A number of seconds, or a sequence of numbers followed by h, m or s
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    if let Ok(seconds) = input.parse::<u64>() {
+        if seconds == 0 {
+            return Err("the duration must be longer than zero".to_string());
+        }
+        return Ok(Duration::from_secs(seconds));
+    }
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' if !number.is_empty() => {
+                let unit = match c {'h' => 3600, 'm' => 60, _ => 1};
+                seconds += number.parse::<u64>().map_err(|error| error.to_string())? * unit;
+                number.clear();
+            },
+            _ => return Err(format!("{input} is not a duration such as 90s, 25m or 1h30m")),
+        }
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(format!("{input} is not a duration such as 90s, 25m or 1h30m"));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_in_seconds_or_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("25m"), Ok(Duration::from_secs(25 * 60)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("ten").is_err());
+    }
+
+    #[test]
+    fn zero_duration_is_refused() {
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("0h0m").is_err());
+        assert!(Cli::try_parse_from(["terminal_stopwatch_and_clock", "--countdown", "0"]).is_err());
+        let cli = Cli::try_parse_from(["terminal_stopwatch_and_clock", "--countdown", "5m"]).unwrap();
+        assert_eq!(cli.countdown, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn watch_interval_must_be_positive() {
+        let parse = |interval: &str| Cli::try_parse_from(["terminal_stopwatch_and_clock", "--output", "json", "--watch", interval]);
+        assert!(parse("0").is_err());
+        assert!(parse("0m").is_err());
+        assert_eq!(parse("10s").unwrap().watch, Some(Duration::from_secs(10)));
+        // The interval only makes sense with an output format
+        assert!(Cli::try_parse_from(["terminal_stopwatch_and_clock", "--watch", "10s"]).is_err());
+    }
+}
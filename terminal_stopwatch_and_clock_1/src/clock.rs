@@ -0,0 +1,41 @@
+// World clocks. The zones are IANA names such as "Europe/Kyiv", so chrono-tz takes care of the daylight saving
+// time and of the offsets which are not a whole number of hours.
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    pub name: String,
+    zone: Tz,
+}
+
+impl Clock {
+    pub fn new(name: &str, zone: &str) -> Result<Self, String> {
+        let zone = zone.trim().parse::<Tz>().map_err(|_| format!("unknown time zone {zone}"))?;
+        Ok(Self { name: name.trim().to_string(), zone })
+    }
+
+    // Parse "Delhi=Asia/Kolkata", or just "Asia/Kolkata" in which case the clock is named after the zone's city
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.split_once('=') {
+            Some((name, zone)) => Self::new(name, zone),
+            None => {
+                let city = input.rsplit('/').next().unwrap_or(input).replace('_', " ");
+                Self::new(&city, input)
+            },
+        }
+    }
+
+    pub fn zone(&self) -> Tz {
+        self.zone
+    }
+
+    pub fn now(&self) -> DateTime<Tz> {
+        Utc::now().with_timezone(&self.zone)
+    }
+
+    // The current time in the zone, followed by its abbreviation which tells whether the DST is in effect
+    pub fn time_pretty(&self) -> String {
+        self.now().format("%Y/%m/%d %H:%M:%S %Z").to_string()
+    }
+}
@@ -0,0 +1,165 @@
+// Settings of the dashboard, read from a json file so that they can be changed without recompiling. Every field
+// has a default value: the file only needs the ones the user wants to change.
+use serde::{Deserialize, Serialize};
+use std::{fs::read_to_string, io, path::Path};
+
+use crate::{stopwatch::Precision, weather::{FORECAST_URL, GEOCODING_URL}};
+
+pub const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub clocks: Vec<ClockConfig>,
+    pub countdown_seconds: u64,
+    pub pomodoro: PomodoroConfig,
+    // The countdown and the Pomodoro keep running while the terminal doesn't have the focus, unless this is set
+    pub pause_timer_on_focus_loss: bool,
+    pub precision: Precision, // Digits of the stopwatch after the seconds
+    pub locations: Vec<LocationConfig>,
+    pub weather_refresh_minutes: u64,
+    pub forecast_url: String, // open-meteo forecast API, which can be replaced by a mirror
+    pub geocoding_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockConfig {
+    pub name: String,
+    pub zone: String, // IANA name of the time zone, like "Europe/Kyiv"
+}
+
+// Location of a weather forecast. Without coordinates, the location is looked up by its name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationConfig {
+    pub name: String,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+impl LocationConfig {
+    // Parse "Paris", "48.85,2.35" or "Home=48.85,2.35"
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let (name, place) = match input.split_once('=') {
+            Some((name, place)) => (name.trim(), place.trim()),
+            None => (input.trim(), input.trim()),
+        };
+        let coordinates = place.split_once(',')
+            .and_then(|(latitude, longitude)| Some((latitude.trim().parse::<f64>().ok()?, longitude.trim().parse::<f64>().ok()?)));
+        match coordinates {
+            Some((latitude, longitude)) if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) => {
+                Ok(Self { name: name.to_string(), latitude: Some(latitude), longitude: Some(longitude) })
+            },
+            Some(_) => Err(format!("{place} are not valid coordinates")),
+            None if name.is_empty() => Err("the location has no name".to_string()),
+            None => Ok(Self { name: name.to_string(), latitude: None, longitude: None }),
+        }
+    }
+}
+
+// Durations of the Pomodoro cycle, in minutes. A long break replaces the short one after every few work sessions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: u64,
+    pub short_break_minutes: u64,
+    pub long_break_minutes: u64,
+    pub long_break_every: usize,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self { work_minutes: 25, short_break_minutes: 5, long_break_minutes: 15, long_break_every: 4 }
+    }
+}
+
+impl ClockConfig {
+    fn new(name: &str, zone: &str) -> Self {
+        Self { name: name.to_string(), zone: zone.to_string() }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clocks: vec![
+                ClockConfig::new("London", "Europe/London"),
+                ClockConfig::new("Kyiv", "Europe/Kyiv"),
+                ClockConfig::new("New York", "America/New_York"),
+            ],
+            countdown_seconds: 300,
+            pomodoro: PomodoroConfig::default(),
+            pause_timer_on_focus_loss: false,
+            precision: Precision::default(),
+            locations: vec![LocationConfig {
+                name: "London".to_string(),
+                latitude: Some(51.509865),
+                longitude: Some(-0.118092),
+            }],
+            weather_refresh_minutes: 30,
+            forecast_url: FORECAST_URL.to_string(),
+            geocoding_url: GEOCODING_URL.to_string(),
+        }
+    }
+}
+
+impl Config {
+    // A missing file gives the default configuration, while an invalid one is reported
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |error: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {error}", path.display()));
+        let config: Self = match read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|error| invalid(error.to_string()))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        config.validate().map_err(invalid)?;
+        Ok(config)
+    }
+
+    // A duration of zero would be over as soon as it starts, like on the command line
+    fn validate(&self) -> Result<(), String> {
+        let pomodoro = &self.pomodoro;
+        let durations = [
+            ("countdown_seconds", self.countdown_seconds),
+            ("pomodoro.work_minutes", pomodoro.work_minutes),
+            ("pomodoro.short_break_minutes", pomodoro.short_break_minutes),
+            ("pomodoro.long_break_minutes", pomodoro.long_break_minutes),
+        ];
+        match durations.iter().find(|(_, duration)| *duration == 0) {
+            Some((name, _)) => Err(format!("{name} must be longer than zero")),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::{remove_file, write}};
+
+    fn load(name: &str, content: &str) -> io::Result<Config> {
+        let path = temp_dir().join(format!("{name}_{}.json", std::process::id()));
+        write(&path, content).unwrap();
+        let config = Config::load(&path);
+        remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn missing_fields_keep_their_default() {
+        let config = load("config_defaults", r#"{"countdown_seconds": 90}"#).unwrap();
+        assert_eq!(config.countdown_seconds, 90);
+        assert_eq!(config.pomodoro.work_minutes, 25);
+        assert!(!config.pause_timer_on_focus_loss);
+    }
+
+    #[test]
+    fn zero_durations_are_refused() {
+        let error = load("config_zero_countdown", r#"{"countdown_seconds": 0}"#).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("countdown_seconds must be longer than zero"), "{error}");
+        let error = load("config_zero_break", r#"{"pomodoro": {"short_break_minutes": 0}}"#).unwrap_err();
+        assert!(error.to_string().ends_with("pomodoro.short_break_minutes must be longer than zero"), "{error}");
+    }
+}
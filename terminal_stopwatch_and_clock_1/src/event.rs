@@ -0,0 +1,39 @@
+// Everything the main loop waits for comes through one channel: the terminal input and the weather updates of the
+// background refresh. The loop sleeps until one of them arrives, or until the tick which follows the time when
+// nothing arrived for a while.
+use crossterm::event::{read, Event};
+use std::{sync::mpsc::{Receiver, RecvError, RecvTimeoutError, Sender}, thread, time::Duration};
+
+use crate::weather::WeatherUpdate;
+
+pub enum AppEvent {
+    Input(Event),
+    Tick,
+    Weather(Box<WeatherUpdate>), // a whole forecast, boxed to keep the other events small
+}
+
+impl From<WeatherUpdate> for AppEvent {
+    fn from(update: WeatherUpdate) -> Self {
+        AppEvent::Weather(Box::new(update))
+    }
+}
+
+// The threads stop once the main loop has dropped the receiver
+pub fn spawn_input(sender: Sender<AppEvent>) {
+    thread::spawn(move || {
+        while let Ok(event) = read() {
+            if sender.send(AppEvent::Input(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+// The tick rate is chosen by the loop for each wait, after what the screen shows
+pub fn next_event(events: &Receiver<AppEvent>, tick_rate: Duration) -> Result<AppEvent, RecvError> {
+    match events.recv_timeout(tick_rate) {
+        Ok(event) => Ok(event),
+        Err(RecvTimeoutError::Timeout) => Ok(AppEvent::Tick),
+        Err(RecvTimeoutError::Disconnected) => Err(RecvError),
+    }
+}
@@ -0,0 +1,240 @@
+// Non-interactive mode, for scripts: the times of the clocks and the hourly forecast of every location are printed
+// once, or again and again with --watch, as JSON, CSV or a compact text table.
+use chrono::SecondsFormat;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{io::{self, stdout, Write}, path::Path, thread, time::{Duration, Instant}};
+
+use crate::{
+    clock::Clock,
+    config::LocationConfig,
+    weather::{load_cache, HourlyPoint, WeatherApp, WeatherSource},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+    Table,
+}
+
+#[derive(Debug, Serialize)]
+struct ClockReport {
+    name: String,
+    zone: String,
+    time: String, // RFC 3339, with the offset of the zone
+    abbreviation: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ForecastReport {
+    location: String,
+    updated_at: Option<String>,
+    error: Option<String>, // Why the forecast couldn't be refreshed, the cached one is given if any
+    hourly: Vec<HourlyPoint>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    clocks: Vec<ClockReport>,
+    forecasts: Vec<ForecastReport>,
+}
+
+const CSV_HEADER: &str = "kind,name,time,temperature_2m,apparent_temperature,precipitation,wind_speed_10m,relative_humidity_2m";
+
+// Quote the fields which would break the columns
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn value(value: Option<f64>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
+
+impl Report {
+    fn new(clocks: &[Clock], weather_apps: &[WeatherApp], hours: Option<usize>) -> Self {
+        Self {
+            clocks: clocks.iter().map(|clock| {
+                let now = clock.now();
+                ClockReport {
+                    name: clock.name.clone(),
+                    zone: clock.zone().name().to_string(),
+                    time: now.to_rfc3339_opts(SecondsFormat::Secs, false),
+                    abbreviation: now.format("%Z").to_string(),
+                }
+            }).collect(),
+            forecasts: weather_apps.iter().map(|weather_app| ForecastReport {
+                location: weather_app.name.clone(),
+                updated_at: weather_app.updated_at().map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                error: weather_app.last_error.clone(),
+                hourly: weather_app.hourly(hours),
+            }).collect(),
+        }
+    }
+
+    // One line per report, so that a watch gives one JSON document per line
+    fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", serde_json::to_string(self).map_err(io::Error::other)?)
+    }
+
+    // The clocks and the forecasts share the columns: a clock only has a name and a time. There is no room for the
+    // errors, which go to the standard error.
+    fn write_csv(&self, out: &mut impl Write, header: bool) -> io::Result<()> {
+        if header {
+            writeln!(out, "{CSV_HEADER}")?;
+        }
+        for forecast in &self.forecasts {
+            if let Some(error) = &forecast.error {
+                eprintln!("{}: {error}", forecast.location);
+            }
+        }
+        for clock in &self.clocks {
+            writeln!(out, "clock,{},{},,,,,", csv_field(&clock.name), clock.time)?;
+        }
+        for forecast in &self.forecasts {
+            for point in &forecast.hourly {
+                writeln!(out, "forecast,{},{},{},{},{},{},{}", csv_field(&forecast.location), point.time,
+                    value(point.temperature_2m), value(point.apparent_temperature), value(point.precipitation),
+                    value(point.wind_speed_10m), value(point.relative_humidity_2m))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        let width = self.clocks.iter().map(|clock| clock.name.chars().count()).max().unwrap_or(0);
+        for clock in &self.clocks {
+            writeln!(out, "{:<width$}  {} {}", clock.name, clock.time, clock.abbreviation)?;
+        }
+        let cell = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{value:.1}"));
+        for forecast in &self.forecasts {
+            writeln!(out)?;
+            match (&forecast.error, &forecast.updated_at) {
+                (Some(error), Some(updated_at)) => writeln!(out, "{} (offline, forecast of {updated_at}: {error})", forecast.location)?,
+                (Some(error), None) => writeln!(out, "{}: {error}", forecast.location)?,
+                (None, _) => writeln!(out, "{}", forecast.location)?,
+            }
+            if forecast.hourly.is_empty() {
+                continue;
+            }
+            writeln!(out, "{:<16} {:>6} {:>6} {:>6} {:>6} {:>6}", "Time", "°C", "Feels", "mm", "km/h", "%")?;
+            for point in &forecast.hourly {
+                writeln!(out, "{:<16} {:>6} {:>6} {:>6} {:>6} {:>6}", point.time, cell(point.temperature_2m),
+                    cell(point.apparent_temperature), cell(point.precipitation), cell(point.wind_speed_10m),
+                    cell(point.relative_humidity_2m))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Options {
+    pub format: OutputFormat,
+    pub watch: Option<Duration>,
+    pub hours: Option<usize>,
+    pub refresh_interval: Duration,
+}
+
+pub fn run(options: Options, clocks: &[Clock], mut locations: Vec<LocationConfig>, source: &WeatherSource,
+    cache_path: &Path) -> io::Result<()> {
+    let mut cache = load_cache(cache_path);
+    let mut weather_apps: Vec<WeatherApp> = locations.iter().map(|location| WeatherApp::new(location, &cache)).collect();
+    let mut fetched_at: Option<Instant> = None;
+    let mut out = stdout().lock();
+    let mut first = true;
+    loop {
+        // The forecast is only fetched again once the refresh interval is over, however often the times are printed
+        if fetched_at.is_none_or(|fetched_at| fetched_at.elapsed() >= options.refresh_interval) {
+            for (weather_app, location) in weather_apps.iter_mut().zip(locations.iter_mut()) {
+                weather_app.update(source.refresh(location, &mut cache, cache_path));
+            }
+            fetched_at = Some(Instant::now());
+        }
+        let report = Report::new(clocks, &weather_apps, options.hours);
+        match options.format {
+            OutputFormat::Json => report.write_json(&mut out)?,
+            OutputFormat::Csv => report.write_csv(&mut out, first)?,
+            OutputFormat::Table => {
+                if !first {
+                    writeln!(out)?;
+                }
+                report.write_table(&mut out)?;
+            },
+        }
+        out.flush()?;
+        first = false;
+        match options.watch {
+            Some(interval) => thread::sleep(interval),
+            None => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report {
+            clocks: vec![ClockReport {
+                name: "New York".to_string(),
+                zone: "America/New_York".to_string(),
+                time: "2025-01-20T07:30:00-05:00".to_string(),
+                abbreviation: "EST".to_string(),
+            }],
+            forecasts: vec![ForecastReport {
+                location: "Home, sweet home".to_string(),
+                updated_at: None,
+                error: None,
+                hourly: vec![HourlyPoint {
+                    time: "2025-01-20T08:00".to_string(),
+                    temperature_2m: Some(4.5),
+                    apparent_temperature: None,
+                    precipitation: Some(0.0),
+                    wind_speed_10m: Some(12.0),
+                    relative_humidity_2m: Some(80.0),
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn writes_csv() {
+        let mut out = Vec::new();
+        report().write_csv(&mut out, true).unwrap();
+        report().write_csv(&mut out, false).unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        assert_eq!(lines, [
+            CSV_HEADER,
+            "clock,New York,2025-01-20T07:30:00-05:00,,,,,",
+            "forecast,\"Home, sweet home\",2025-01-20T08:00,4.5,,0,12,80",
+            "clock,New York,2025-01-20T07:30:00-05:00,,,,,",
+            "forecast,\"Home, sweet home\",2025-01-20T08:00,4.5,,0,12,80",
+        ]);
+    }
+
+    #[test]
+    fn writes_one_json_document_per_line() {
+        let mut out = Vec::new();
+        report().write_json(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["clocks"][0]["zone"], "America/New_York");
+        assert_eq!(json["forecasts"][0]["hourly"][0]["temperature_2m"], 4.5);
+        assert!(json["forecasts"][0]["hourly"][0]["apparent_temperature"].is_null());
+    }
+
+    #[test]
+    fn writes_a_table() {
+        let mut out = Vec::new();
+        report().write_table(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("New York  2025-01-20T07:30:00-05:00 EST\n\nHome, sweet home\n"), "{text}");
+        assert!(text.ends_with("2025-01-20T08:00    4.5      -    0.0   12.0   80.0\n"), "{text}");
+    }
+}
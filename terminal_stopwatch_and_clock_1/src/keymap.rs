@@ -0,0 +1,355 @@
+// Key bindings of the dashboard. The keys act on the focused pane: its own bindings come first, then the global ones.
+// A json file can bind any key to any action, for example {"stopwatch": {"s": "start_stop"}, "global": {"x": "quit"}}.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs::read_to_string, io, path::Path};
+
+pub const KEYMAP_FILE: &str = "keymap.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Help,
+    FocusNext,
+    FocusPrevious,
+    Alarms,
+    StartStop,
+    Pause,
+    Lap,
+    ToggleTimer,
+    TimerMode,
+    AddMinute,
+    RemoveMinute,
+    Report,
+    ScrollUp,
+    ScrollDown,
+    PreviousLocation,
+    NextLocation,
+    NextVariable,
+    NextOverlay,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide this help",
+            Action::FocusNext => "Focus the next pane",
+            Action::FocusPrevious => "Focus the previous pane",
+            Action::Alarms => "Open the alarms",
+            Action::StartStop => "Start, stop or reset",
+            Action::Pause => "Pause or resume",
+            Action::Lap => "Record a lap",
+            Action::ToggleTimer => "Switch between the stopwatch and the timer",
+            Action::TimerMode => "Switch between the countdown and the Pomodoro cycle",
+            Action::AddMinute => "Add a minute to the countdown",
+            Action::RemoveMinute => "Remove a minute from the countdown",
+            Action::Report => "Show the time logged per label",
+            Action::ScrollUp => "Select the previous lap",
+            Action::ScrollDown => "Select the next lap",
+            Action::PreviousLocation => "Show the previous location",
+            Action::NextLocation => "Show the next location",
+            Action::NextVariable => "Show the next weather variable",
+            Action::NextOverlay => "Overlay the feels like temperature or every location",
+        }
+    }
+}
+
+// The parts of the screen which can have the focus. The stopwatch pane also shows the timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pane {
+    Stopwatch,
+    Clocks,
+    Chart,
+}
+
+impl Pane {
+    const ALL: [Pane; 3] = [Pane::Stopwatch, Pane::Clocks, Pane::Chart];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pane::Stopwatch => "Stopwatch and timer",
+            Pane::Clocks => "Clocks",
+            Pane::Chart => "Weather",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Pane::Stopwatch => Pane::Clocks,
+            Pane::Clocks => Pane::Chart,
+            Pane::Chart => Pane::Stopwatch,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        self.next().next()
+    }
+}
+
+// A key with its modifiers. The shift is part of the character itself, like '?' or 'A': "shift+a" is 'A'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+            code => code,
+        };
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    // Parse "q", "?", "enter", "space", "f1" or "ctrl+c"
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = input;
+        while let Some((modifier, rest)) = name.split_once('+').filter(|(_, rest)| !rest.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {modifier} in {input}")),
+            };
+            name = rest;
+        }
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            // The shifted symbols depend on the layout, they are bound as they are typed: "?" rather than "shift+/"
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) && !c.is_alphabetic() => {
+                return Err(format!("{input} can't be bound, bind the character typed with shift instead"));
+            },
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                function => match function.strip_prefix('f').and_then(|number| number.parse().ok()) {
+                    Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+                    _ => return Err(format!("unknown key {input}")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl+"), (KeyModifiers::ALT, "Alt+"), (KeyModifiers::SHIFT, "Shift+")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+// The bindings file, where every section is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeymapFile {
+    global: HashMap<String, Action>,
+    stopwatch: HashMap<String, Action>,
+    clocks: HashMap<String, Action>,
+    chart: HashMap<String, Action>,
+}
+
+// The bindings are kept in order for the help
+type Bindings = Vec<(Key, Action)>;
+
+pub struct Keymap {
+    global: Bindings,
+    panes: HashMap<Pane, Bindings>,
+}
+
+fn bindings(list: &[(&str, Action)]) -> Bindings {
+    list.iter().map(|(key, action)| (Key::parse(key).expect("invalid default key"), *action)).collect()
+}
+
+// A key bound in the file replaces the default binding of that key
+fn merge(bindings: &mut Bindings, overrides: HashMap<String, Action>) -> Result<(), String> {
+    let mut overrides = overrides.into_iter()
+        .map(|(key, action)| Ok((Key::parse(&key)?, action)))
+        .collect::<Result<Vec<_>, String>>()?;
+    // The order of the keys of the file is lost, sort them to keep the help stable
+    overrides.sort_by_key(|(key, _)| key.to_string());
+    for (key, action) in overrides {
+        match bindings.iter_mut().find(|(bound, _)| *bound == key) {
+            Some(binding) => binding.1 = action,
+            None => bindings.push((key, action)),
+        }
+    }
+    Ok(())
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        Self {
+            global: bindings(&[("esc", Quit), ("q", Quit), ("?", Help), ("tab", FocusNext), ("backtab", FocusPrevious), ("a", Alarms)]),
+            panes: HashMap::from([
+                (Pane::Stopwatch, bindings(&[
+                    ("enter", StartStop), ("p", Pause), ("space", Pause), ("l", Lap), ("up", ScrollUp), ("down", ScrollDown),
+                    ("t", ToggleTimer), ("m", TimerMode), ("+", AddMinute), ("-", RemoveMinute),
+                    ("r", Report),
+                ])),
+                (Pane::Clocks, bindings(&[("enter", Alarms)])),
+                (Pane::Chart, bindings(&[
+                    ("left", PreviousLocation), ("right", NextLocation), ("v", NextVariable), ("o", NextOverlay),
+                ])),
+            ]),
+        }
+    }
+}
+
+impl Keymap {
+    // A missing file gives the default bindings, while an invalid one is reported
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |error: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {error}", path.display()));
+        let file: KeymapFile = match read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|error| invalid(error.to_string()))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        let mut keymap = Self::default();
+        merge(&mut keymap.global, file.global).map_err(invalid)?;
+        for (pane, overrides) in [(Pane::Stopwatch, file.stopwatch), (Pane::Clocks, file.clocks), (Pane::Chart, file.chart)] {
+            merge(keymap.panes.entry(pane).or_default(), overrides).map_err(invalid)?;
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, pane: Pane, event: KeyEvent) -> Option<Action> {
+        let key = Key::from(event);
+        self.panes.get(&pane).into_iter().flatten()
+            .chain(self.global.iter())
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, action)| *action)
+    }
+
+    // The first key bound to the action, to be shown on the screen
+    pub fn key_for(&self, action: Action) -> String {
+        self.global.iter()
+            .chain(Pane::ALL.iter().filter_map(|pane| self.panes.get(pane)).flatten())
+            .find(|(_, bound)| *bound == action)
+            .map_or("unbound".to_string(), |(key, _)| key.to_string())
+    }
+
+    // Sections of the help: the global bindings, then those of each pane
+    pub fn help(&self) -> Vec<(&'static str, Vec<(String, &'static str)>)> {
+        let lines = |bindings: &Bindings| bindings.iter()
+            .map(|(key, action)| (key.to_string(), action.description()))
+            .collect();
+        let mut sections = vec![("Everywhere", lines(&self.global))];
+        for pane in Pane::ALL {
+            sections.push((pane.name(), self.panes.get(&pane).map(lines).unwrap_or_default()));
+        }
+        sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(Key::parse("q"), Ok(Key::new(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert_eq!(Key::parse("space"), Ok(Key::new(KeyCode::Char(' '), KeyModifiers::NONE)));
+        assert_eq!(Key::parse("Ctrl+c"), Ok(Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert_eq!(Key::parse("+"), Ok(Key::new(KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert_eq!(Key::parse("f5"), Ok(Key::new(KeyCode::F(5), KeyModifiers::NONE)));
+        assert!(Key::parse("f13").is_err());
+        assert!(Key::parse("hyper+x").is_err());
+        assert_eq!(Key::parse("ctrl+c").unwrap().to_string(), "Ctrl+c");
+        // The shift which makes the '?' doesn't matter
+        assert_eq!(Key::from(press(KeyCode::Char('?'), KeyModifiers::SHIFT)), Key::parse("?").unwrap());
+    }
+
+    #[test]
+    fn shifted_letters_are_the_uppercase_ones() {
+        let shifted_a = Key::from(press(KeyCode::Char('A'), KeyModifiers::SHIFT));
+        assert_eq!(Key::parse("shift+a"), Ok(shifted_a));
+        assert_eq!(Key::parse("Shift+A"), Ok(shifted_a));
+        assert_eq!(Key::parse("A"), Ok(shifted_a));
+        assert_ne!(Key::parse("a"), Ok(shifted_a));
+        assert_eq!(Key::parse("ctrl+shift+é").unwrap().to_string(), "Ctrl+É");
+        assert!(Key::parse("shift+/").is_err());
+        assert!(Key::parse("shift+1").is_err());
+        // Shift still matters with the keys which aren't characters
+        assert_ne!(Key::parse("shift+up"), Key::parse("up"));
+    }
+
+    #[test]
+    fn the_focused_pane_comes_first() {
+        let keymap = Keymap::default();
+        let enter = press(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(keymap.action(Pane::Stopwatch, enter), Some(Action::StartStop));
+        assert_eq!(keymap.action(Pane::Clocks, enter), Some(Action::Alarms));
+        assert_eq!(keymap.action(Pane::Chart, enter), None);
+        // The chart keys do nothing elsewhere, while the global ones work everywhere
+        assert_eq!(keymap.action(Pane::Stopwatch, press(KeyCode::Char('v'), KeyModifiers::NONE)), None);
+        assert_eq!(keymap.action(Pane::Chart, press(KeyCode::Char('q'), KeyModifiers::NONE)), Some(Action::Quit));
+    }
+
+    #[test]
+    fn the_file_overrides_the_defaults() {
+        let path = temp_dir().join(format!("keymap_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"stopwatch": {"s": "start_stop", "enter": "lap"}, "global": {"ctrl+q": "quit"}}"#).unwrap();
+        let keymap = Keymap::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(keymap.action(Pane::Stopwatch, press(KeyCode::Char('s'), KeyModifiers::NONE)), Some(Action::StartStop));
+        assert_eq!(keymap.action(Pane::Stopwatch, press(KeyCode::Enter, KeyModifiers::NONE)), Some(Action::Lap));
+        assert_eq!(keymap.action(Pane::Chart, press(KeyCode::Char('q'), KeyModifiers::CONTROL)), Some(Action::Quit));
+        assert_eq!(keymap.key_for(Action::StartStop), "s");
+    }
+
+    #[test]
+    fn reports_invalid_files() {
+        let path = temp_dir().join(format!("keymap_invalid_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"chart": {"hyper+x": "quit"}}"#).unwrap();
+        assert!(Keymap::load(&path).is_err());
+        std::fs::write(&path, r#"{"chart": {"x": "explode"}}"#).unwrap();
+        assert!(Keymap::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,358 +1,507 @@
-use crossterm::{event::{self, poll, read, Event, KeyCode, KeyEventKind}, execute,
-    terminal::{ disable_raw_mode, enable_raw_mode, Clear, ClearType}};
-use std::{ fmt::Debug, io::stdout, error::Error, thread::sleep, time::{Duration, Instant}, borrow::Cow};
-use chrono::{offset::{Utc, FixedOffset}, naive::NaiveDateTime};
+mod alarm;
+mod alarm_panel;
+mod chart;
+mod cli;
+mod clock;
+mod config;
+mod event;
+mod headless;
+mod keymap;
+mod report_panel;
+mod session_log;
+mod stopwatch;
+mod timer;
+mod weather;
+
+use chrono::{Local, Utc};
+use crossterm::{event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode,
+    KeyEventKind, MouseButton, MouseEvent, MouseEventKind}, execute, style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}};
+use std::{io::{self, stdout}, panic, sync::mpsc::channel, time::Duration};
+use clap::Parser;
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    widgets::{Block, Borders, Paragraph, Axis, Chart, Dataset, GraphType},
+    layout::{Constraint, Direction, Layout, Position, Rect},
+    widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState, Tabs},
     style::{Style, Stylize},
-    symbols,
-    text::Span,
+    text::Line,
+    Frame,
     Terminal
 };
-use serde::{Deserialize, Serialize};
-use reqwest::{blocking::get, StatusCode};
-
-struct Stopwatch {
-    now: Instant,
-    state: StopwatchState,
-    display: Cow<'static, str>, // cast into a Cow to optimize the performance later enabling static allocation
-    paused_time: Duration,// Track the pause duration to adjust the stopwatch when there is a change of focus
-}
+use alarm::{Alarm, Alarms};
+use alarm_panel::{render_alert, save_error, AlarmPanel};
+use chart::{forecast_chart, now_marker, Overlay, Series};
+use cli::Cli;
+use clock::Clock;
+use config::Config;
+use event::{next_event, spawn_input, AppEvent};
+use keymap::{Action, Keymap, Pane};
+use report_panel::{LabelPrompt, ReportPanel};
+use session_log::SessionLog;
+use stopwatch::{format_elapsed, Precision, Stopwatch};
+use timer::{Timer, TimerMode};
+use weather::{load_cache, spawn_refresh, WeatherApp, WeatherSource, WeatherVariable};
 
-const URL: &str = "https://api.open-meteo.com/v1/forecast?";
+// The clocks and the timer change every second, the screen is checked a few times per second for them. The running
+// stopwatch is followed at its precision, but it can't be shown more often than the fastest tick.
+const IDLE_TICK: Duration = Duration::from_millis(250);
+const FASTEST_TICK: Duration = Duration::from_millis(10);
 
+// Raw mode and the alternate screen are left when the guard is dropped, whether the loop ended normally or on an
+// error. A panic restores the terminal before its message is printed, or the message would be lost with the
+// alternate screen.
+struct TerminalGuard;
 
-
-#[derive(Debug, Deserialize, Default, Serialize)]
-struct WeatherForecast {
-    latitude: f64,
-    longitude: f64,
-    timezone: String,
-    hourly: HourlyData,
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        let hook=panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            hook(info);
+        }));
+        let guard=TerminalGuard;
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, EnableFocusChange, EnableMouseCapture)?;
+        Ok(guard)
+    }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-struct HourlyData {
-    time: Vec<String>,          // Vec of Strings for time
-    temperature_2m: Vec<f64>,   // Vec of f64 for temperatures
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
 }
 
-#[derive(Debug, Default)]
-struct App {
-    search_latitude: f64,
-    search_longitude: f64,
-    forecast_weather: WeatherForecast,
-    last_error: Option<String>,
+fn restore_terminal() {
+    let _=disable_raw_mode();
+    let _=execute!(stdout(), DisableMouseCapture, DisableFocusChange, LeaveAlternateScreen);
+}
+// What changes on the screen with the time alone. The screen is only drawn again when it differs, or after an event.
+fn frame_key(stopwatch: &Stopwatch, timer: &Timer) -> (String, u128, bool, i64) {
+    (stopwatch.get_time(), timer.remaining().as_millis().div_ceil(1000), timer.is_flashing(), Local::now().timestamp())
 }
-impl App{
-    fn new(search_latitude: f64, search_longitude: f64) -> Result<Self, std::io::Error> {
-        let forecast_weather=WeatherForecast::default();
-        Ok(Self{
-            search_latitude: search_latitude,
-            search_longitude: search_longitude,
-            forecast_weather,
-            last_error: None,
-        })
+// Half the step of the stopwatch shown, so that none of its digits is skipped
+fn tick_rate(stopwatch: &Stopwatch, show_timer: bool) -> Duration {
+    if show_timer || !stopwatch.is_running() {
+        return IDLE_TICK;
     }
-    fn get_weather(&mut self) -> Result<(), Box<dyn Error>>{
-        let url=format!("{URL}latitude={}&longitude={}&hourly=temperature_2m", &self.search_latitude, 
-        &self.search_longitude);
-        match get(&url) {
-            Ok(response) => {
-                match response.status() {
-                    StatusCode::OK => {
-                        let text=response.text()?;
-                        if let Ok(weather) = serde_json::from_str::<WeatherForecast>(&text) {
-                            self.forecast_weather=weather;
-                            self.last_error = None;
-                    } else {self.last_error =Some("Error! Received an invalid json format".to_string());}
-                    },
-                    StatusCode::BAD_REQUEST => {self.last_error =Some("Error 400: Bad request".to_string());},
-                    StatusCode::UNAUTHORIZED => {self.last_error =Some("Error 401: Unauthorized".to_string());},
-                    StatusCode::FORBIDDEN => {self.last_error=Some("Error 403: Forbidden".to_string());},
-                    StatusCode::NOT_FOUND => {self.last_error=Some("Error 404: Not Found".to_string());},
-                    StatusCode::TOO_MANY_REQUESTS => {
-                        //The API documentation says that number of requests per day is limited
-                        self.last_error=Some("Error 429: Too Many Requests".to_string());},
-                    StatusCode::REQUEST_TIMEOUT => {self.last_error=Some("Error 408: Request Timeout".to_string());},
-                    StatusCode::INTERNAL_SERVER_ERROR => {self.last_error=Some("Error 500: Internal Server Error".to_string());},
-                    StatusCode::BAD_GATEWAY => {self.last_error=Some("Error 502: Bad Gateway".to_string());},
-                    StatusCode::SERVICE_UNAVAILABLE => {self.last_error=Some("Error 503: Service Unavailable".to_string());},
-                    StatusCode::GATEWAY_TIMEOUT => {self.last_error=Some("Error 504: Gateway Timeout".to_string());},
-                    _ => {//We will not mind about other HTTP errors 
-                        self.last_error=Some("Error: Unknown HTTP Error".to_string());},
-                }
-            },
-            Err(error) => {
-                if error.is_connect() {self.last_error=Some("No internet connection".to_string());} else if error.is_timeout() {
-                    self.last_error=Some("Request timed out".to_string());} else {
-                        self.last_error=Some(format!("Error: {}", error));
-                }
-                    
-                }
-            }
-            Ok(())
-        }
-        // function to prepare the format of the data to be incorporated into the ratatui chart object
-        fn get_dataset(&self) -> Vec<(f64,f64)>{
-            //Get access to the time vec from the token
-            let time=&self.forecast_weather.hourly.time ;
-            //Get access to the temperature_2m vec from the token
-            let temperature_2m=&self.forecast_weather.hourly.temperature_2m;
-            //Choose a reference datetime to proceed with the conversion of dates to floats 
-            let reference_datetime=NaiveDateTime::parse_from_str(&time[0], "%Y-%m-%dT%H:%M")
-            .expect("Invalid datetime format");
-            let temperature_dataset: Vec<(f64,f64)>=time.iter()
-            .zip(temperature_2m.iter())
-            .map(|(t,&temp)| {
-                let parsed_datetime= NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M")
-                .expect("Invalid datetime format");
-                // Calculate the difference between the parsed datetime and reference datetime
-                let duration = parsed_datetime.signed_duration_since(reference_datetime);
-                //let parsed_datetime=NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
-                //.expect("Invalid datetime format");
-                let x=duration.num_seconds() as f64 /3600.0;
-                (x,temp)
-            }).collect();
-            temperature_dataset
-        }
-        // Function to prepare the labels for the x axis to incorporate it into the Axis object of ratatui crate.
-        fn get_x_labels(&self) -> Vec<Span> {
-            let times = &self.forecast_weather.hourly.time;
-            if times.is_empty() {
-                return vec![];
-            }
-            // Let's choose the first, middle, and last labels. Otherwise the labels we will get a lot of labels
-            // which cannot be displayed later in the graph due to the overlapping of text.
-            let first = times.first().unwrap();
-            let middle = times.get(times.len()/2).unwrap();
-            let last = times.last().unwrap();
-            vec![Span::raw(first), Span::raw(middle), Span::raw(last)]
-        }
-        // Function to prepare the labels for the y axis to incorporate it into the Axis object of ratatui crate.
-        fn get_y_labels(&self) -> Vec<Span> {
-            // Prepare the labels for the sorting operation
-            let mut y_labels: Vec<f64>=self.forecast_weather.hourly.temperature_2m.iter().cloned().collect();
-            // Sort the labels in order to display them correctly in the y axis of the chart
-            y_labels.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            
-            let sorted_labels: Vec<Span> = y_labels.iter().map(|&label| Span::raw(format!("{:.1}", label)))
-            .collect();
-            sorted_labels
-        }
+    (stopwatch.precision().step()/2).clamp(FASTEST_TICK, IDLE_TICK)
+}
+// Define this function just for readability in the main function below.
+fn block_with(input: &str) -> Block<'_> {
+    Block::default().title(input).borders(Borders::ALL)
 }
-enum StopwatchState {
-    NotStarted,
-    Running,
-    Done,
+// The blocks of the focused pane stand out
+fn pane_block(input: &str, focused: bool) -> Block<'_> {
+    let block=block_with(input);
+    if focused {block.border_style(Style::default().yellow())} else {block}
 }
-
-impl Stopwatch {
-    fn new()->Self {
-        Self {
-            now: Instant::now(),
-            state: StopwatchState::NotStarted,
-            display: Cow::Borrowed("0:00:00"),
-            paused_time: Duration::ZERO,
-        }
-    }
-    // Convert the String output to a string literal with a static lifetime to enable caching and use
-    // Cow to lets you return a &str if you don’t need an owned String
-    fn get_time(&self) -> Cow<'static ,str> { 
-        use StopwatchState::*;
-        match self.state {
-            NotStarted => Cow::Borrowed("00:00:00"),
-            Running => {
-                let mut elapsed=self.now.elapsed().as_millis()+self.paused_time.as_millis();
-                let minutes=elapsed / 60000;
-                elapsed-=minutes*60000;
-                let seconds = elapsed/1000;
-                elapsed-=seconds*1000;
-                let split_seconds=elapsed/10;
-                // Show at least two digits in the stopwatch 
-                Cow::Owned(format!("{:02}:{:02}:{:02}",minutes,seconds,split_seconds))
-            },
-            Done => Cow::Owned(self.display.to_string()),
-        }
+// Area of the given size in the middle of another one, shrunk to fit in it
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width=width.min(area.width);
+    let height=height.min(area.height);
+    Rect::new(area.x+(area.width-width)/2, area.y+(area.height-height)/2, width, height)
+}
+// Every binding of the keymap, in a popup closed by any key
+fn render_help(f: &mut Frame, keymap: &Keymap) {
+    let mut lines=Vec::new();
+    for (section, bindings) in keymap.help() {
+        lines.push(Line::from(section.bold()));
+        lines.extend(bindings.into_iter().map(|(key, description)| Line::from(format!("  {key:<10}{description}"))));
     }
-    fn next_state(&mut self) {
-        use StopwatchState::*;
-        match self.state {
-            NotStarted => {
-                self.now=Instant::now();
-                self.state=Running;
-            },
-            Running => {
-                self.display=self.get_time();
-                self.state=Done;
-            },
-            Done => {
-                self.state=NotStarted;
-                self.paused_time = Duration::ZERO;
-            },
-        }
+    let area=popup_area(f.area(), 70, lines.len() as u16+2);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block_with("Keys (press any key to close)")), area);
+}
+// The timer takes the whole left panel so that the flashing block can't be missed when the time is up
+fn timer_panel<'a>(timer: &Timer, block: Block<'a>, start_key: &str) -> Paragraph<'a> {
+    // The seconds are rounded up so that the countdown shows 00:00 only once the time is up
+    let remaining=Duration::from_secs(timer.remaining().as_millis().div_ceil(1000) as u64);
+    let mut lines=vec![format!("{}: {}", timer.phase().name(), format_elapsed(remaining, Precision::Seconds))];
+    if timer.mode()==TimerMode::Pomodoro {
+        let (work_sessions, short_breaks, long_breaks)=timer.counts();
+        lines.push(format!("Pomodoros: {work_sessions}  Short breaks: {short_breaks}  Long breaks: {long_breaks}"));
     }
-    //pause method to pause the stopwatch when the focus is lost
-    fn pause(&mut self) {
-        if let StopwatchState::Running = self.state {
-            self.paused_time += self.now.elapsed();
-            self.state=StopwatchState::NotStarted;
-            
-        }
+    if timer.is_finished() {
+        let next=if timer.mode()==TimerMode::Pomodoro {"start the next phase"} else {"reset the timer"};
+        lines.push(format!("Time is up! Press {start_key} to {next}."));
+    } else if timer.is_paused() {
+        lines.push("Paused".to_string());
     }
-    //resume method to resume the stopwatch count when the focus is gained
-    fn resume(&mut self) {
-        if let StopwatchState::NotStarted = self.state {
-            self.now=Instant::now();
-            self.state=StopwatchState::Running;
+    let panel=Paragraph::new(lines.join("\n")).block(block);
+    if timer.is_flashing() {panel.black().on_red()} else {panel}
+}
+// Table of the laps, the best one in green and the worst one in red
+fn lap_table<'a>(stopwatch: &Stopwatch, block: Block<'a>) -> Table<'a> {
+    let best_and_worst=stopwatch.best_and_worst_laps();
+    let rows: Vec<Row>=stopwatch.laps().iter().map(|lap| {
+        let row=Row::new(vec![lap.number.to_string(), format_elapsed(lap.time, stopwatch.precision()), format_elapsed(lap.total, stopwatch.precision())]);
+        match best_and_worst {
+            Some((best, _)) if best==lap.number => row.green(),
+            Some((_, worst)) if worst==lap.number => row.red(),
+            _ => row,
         }
-    }
+    }).collect();
+    Table::new(rows, [Constraint::Length(5), Constraint::Length(10), Constraint::Length(10)])
+    .header(Row::new(vec!["Lap", "Lap time", "Total"]).bold())
+    .row_highlight_style(Style::default().reversed())
+    .block(block)
 }
-
-
-// Define this function just for readability in the main function below.
-fn block_with(input: &str) -> Block {
-    Block::default().title(input).borders(Borders::ALL)
+// Lines of the weather chart and the title of its y axis
+fn chart_series(weather_apps: &[WeatherApp], selected: usize, variable: WeatherVariable, overlay: Overlay) -> (Vec<Series>, String) {
+    let weather_app=&weather_apps[selected];
+    let series=|weather_app: &WeatherApp, variable: WeatherVariable, name: String| Series {
+        name,
+        data: weather_app.get_dataset(variable),
+    };
+    match overlay {
+        Overlay::None => (vec![series(weather_app, variable, variable.name().to_string())],
+            format!("{} ({})", variable.name(), variable.unit())),
+        Overlay::FeelsLike => (
+            [WeatherVariable::Temperature, WeatherVariable::ApparentTemperature].into_iter()
+                .map(|variable| series(weather_app, variable, variable.name().to_string()))
+                .collect(),
+            format!("Temperature ({})", WeatherVariable::Temperature.unit())),
+        Overlay::Locations => (
+            weather_apps.iter().map(|weather_app| series(weather_app, variable, weather_app.name.clone())).collect(),
+            format!("{} ({})", variable.name(), variable.unit())),
+    }
 }
-// Define this function just for readability in the main function below.
-fn time_pretty(h: i32) -> String { // I have changed the name og the function to handle other time zones.
-    let offset = FixedOffset::east_opt(h * 3600).unwrap_or_else(|| FixedOffset::east_opt(0)
-                                .unwrap()); // Ensure valid offset
-    Utc::now().with_timezone(&offset).format("%Y/%m/%d %H:%M:%S").to_string()
+// Split the area into a grid of boxes, one per clock. The clocks are stacked as long as they fit in the upper half
+// of the area, then they are laid out on several columns.
+fn clock_areas(area: Rect, count: usize) -> (Vec<Rect>, Rect) {
+    const CLOCK_HEIGHT: usize = 3;
+    if count == 0 {
+        return (Vec::new(), area);
+    }
+    let max_rows = (area.height as usize / 2 / CLOCK_HEIGHT).max(1);
+    let columns = count.div_ceil(max_rows);
+    let rows = count.div_ceil(columns);
+    let split = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length((rows * CLOCK_HEIGHT) as u16), Constraint::Min(0)])
+        .split(area);
+    let row_areas = Layout::default().direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(CLOCK_HEIGHT as u16); rows])
+        .split(split[0]);
+    let areas = row_areas.iter()
+        .flat_map(|row| Layout::default().direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+            .split(*row).to_vec())
+        .take(count)
+        .collect();
+    (areas, split[1])
 }
 // Using anyhow::Error to propagate multiple types of errors
 fn main() -> Result<(), anyhow::Error> {
+    let cli=Cli::parse();
+    // The clocks given on the command line replace the ones of the configuration file
+    let config=Config::load(&cli.config)?;
+    let clocks: Vec<Clock>=if cli.clocks.is_empty() {
+        config.clocks.iter()
+            .map(|clock| Clock::new(&clock.name, &clock.zone))
+            .collect::<Result<_, _>>()
+            .map_err(anyhow::Error::msg)?
+    } else {cli.clocks};
     let stdout=stdout();
     let backend=CrosstermBackend::new(&stdout);
-    // Latitude and longitude of London are entered below.
-    let mut app_london=App::new(51.509865, -0.118092)?;
-    let _weather_london=app_london.get_weather();
-    let data=app_london.get_dataset();
-    // Define the bounds of the chart to display.
-    let (x_min, x_max) = data.iter()
-        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (x, _)| {
-            (min.min(*x), max.max(*x))
-        });
-    
-    let (y_min, y_max) = data.iter()
-    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (_, y)| {
-            (min.min(*y), max.max(*y))
-        });
+    // The locations given on the command line replace the ones of the configuration file
+    let locations=if cli.locations.is_empty() {config.locations.clone()} else {cli.locations.clone()};
+    if locations.is_empty() {
+        anyhow::bail!("No weather location configured in {}", cli.config.display());
+    }
+    // The cached forecasts are shown until the background thread brings fresh ones
+    let cache=load_cache(&cli.weather_cache);
+    let mut weather_apps: Vec<WeatherApp>=locations.iter().map(|location| WeatherApp::new(location, &cache)).collect();
+    let source=WeatherSource {
+        forecast_url: cli.forecast_url.clone().unwrap_or(config.forecast_url.clone()),
+        geocoding_url: cli.geocoding_url.clone().unwrap_or(config.geocoding_url.clone()),
+    };
+    let refresh_interval=Duration::from_secs(cli.refresh_minutes.unwrap_or(config.weather_refresh_minutes).max(1)*60);
+    if let Some(format)=cli.output {
+        let options=headless::Options { format, watch: cli.watch, hours: cli.hours, refresh_interval };
+        return Ok(headless::run(options, &clocks, locations, &source, &cli.weather_cache)?);
+    }
+    let (sender, events)=channel();
+    spawn_refresh(locations, source, refresh_interval, cli.weather_cache.clone(), sender.clone());
+    // Location and variable shown on the chart
+    let mut selected_location=0;
+    let mut variable=WeatherVariable::Temperature;
+    let mut overlay=Overlay::None;
+    let mut alarms=Alarms::load(&cli.alarms, Utc::now())?;
+    // The alarms popup when it is open, and the alarms which rang until they are dismissed
+    let mut alarm_panel: Option<AlarmPanel>=None;
+    let mut ringing: Vec<Alarm>=Vec::new();
+    let mut ringing_error: Option<String>=None;
+    let keymap=Keymap::load(&cli.keymap)?;
+    let mut focus=Pane::Stopwatch;
+    let mut show_help=false;
+    // Every stopped timing is logged, once its label is typed in the prompt
+    let session_log=SessionLog::new(&cli.sessions);
+    let mut label_prompt: Option<LabelPrompt>=None;
+    let mut report_panel: Option<ReportPanel>=None;
+    // Where each pane was last drawn, to find the one which is clicked
+    let mut pane_areas: Vec<(Pane, Rect)>=Vec::new();
     
     // The ratatui terminal takes a crossterm backend.
     let mut terminal=Terminal::new(backend)?;
-    let mut stopwatch=Stopwatch::new();
-    // Enable raw mode and focus event detection
-    enable_raw_mode()?;
-    execute!(&stdout, event::EnableFocusChange, Clear(ClearType::All))?;
+    let mut stopwatch=Stopwatch::new(cli.precision.unwrap_or(config.precision));
+    let countdown=cli.countdown.unwrap_or(Duration::from_secs(config.countdown_seconds));
+    let mut timer=Timer::new(countdown, config.pomodoro);
+    // The left panel shows either the stopwatch or the timer
+    let mut show_timer=false;
+    // Selected row of the lap table, which scrolls to keep it visible
+    let mut lap_table_state=TableState::default();
+    // Enable raw mode, the alternate screen and focus event detection
+    let guard=TerminalGuard::enter()?;
+    spawn_input(sender);
+    let mut drawn=None;
+    // The second in which the alarms were last checked
+    let mut alarms_checked=None;
     loop{
-        if poll(Duration::from_millis(0))? {
-            let event=read()?;
-            match event {
-                /* Warning: Capturing FocusGained and FocusLost events are not warranted for all terminals.
-                In my case, I tested this program with cmd terminal included in vs code but both events were
-                not captured. But when I used the standalone version of the cmd it worked perfectly.
-                This might be a lesson to take: Whenever you use crossterm crate try to test your program
-                in a standalone terminal*/
-                Event::FocusLost => {
-                    stopwatch.pause();
-                },
+        // Nothing is done until an event arrives or the tick is due
+        let mut redraw=match next_event(&events, tick_rate(&stopwatch, show_timer))? {
+            AppEvent::Input(event) => {
+                let action=match event {
+                    /* Warning: Capturing FocusGained and FocusLost events are not warranted for all terminals.
+                    In my case, I tested this program with cmd terminal included in vs code but both events were
+                    not captured. But when I used the standalone version of the cmd it worked perfectly.
+                    This might be a lesson to take: Whenever you use crossterm crate try to test your program
+                    in a standalone terminal*/
+                    Event::FocusLost => {
+                        stopwatch.pause();
+                        // The bell must ring while the user works in another window, unless asked otherwise
+                        if config.pause_timer_on_focus_loss {
+                            timer.pause();
+                        }
+                        None
+                    },
 
-                Event::FocusGained => {
-                    stopwatch.resume();
-                },
-                Event::Key(key_event) => {
-                    match (key_event.code, key_event.kind) {
-                        (KeyCode::Enter, KeyEventKind::Press) =>{
-                            stopwatch.next_state();
-                            println!("Enter key pressed")
-                        },
-                        (KeyCode::Esc, KeyEventKind::Press) =>{
-                            break;// we absolutely must add a break condition to the infinite loop in order to disable the raw mode later
-                        },
-                        _ => {},
-                    }
-                },
-                _ => {}
+                    Event::FocusGained => {
+                        stopwatch.resume();
+                        timer.resume();
+                        None
+                    },
+                    Event::Key(key_event) if key_event.kind!=KeyEventKind::Press => None,
+                    // An alarm which rings takes the keys until it is dismissed, then the help, the label prompt and
+                    // the popups when they are open
+                    Event::Key(key_event) if !ringing.is_empty() => {
+                        if key_event.code==KeyCode::Enter {
+                            ringing.clear();
+                            ringing_error=None;
+                        }
+                        None
+                    },
+                    Event::Key(_) if show_help => {
+                        show_help=false;
+                        None
+                    },
+                    Event::Key(key_event) if label_prompt.is_some() => {
+                        if let Some(prompt)=&mut label_prompt {
+                            if !prompt.handle_key(key_event.code, &session_log) {
+                                label_prompt=None;
+                            }
+                        }
+                        None
+                    },
+                    Event::Key(key_event) if report_panel.is_some() => {
+                        if let Some(panel)=&mut report_panel {
+                            let closes=keymap.action(focus, key_event)==Some(Action::Report);
+                            if !panel.handle_key(key_event.code, closes, &session_log, &cli.report_csv) {
+                                report_panel=None;
+                            }
+                        }
+                        None
+                    },
+                    Event::Key(key_event) if alarm_panel.is_some() => {
+                        if let Some(panel)=&mut alarm_panel {
+                            let closes=keymap.action(focus, key_event)==Some(Action::Alarms);
+                            if !panel.handle_key(key_event.code, closes, &mut alarms, &clocks) {
+                                alarm_panel=None;
+                            }
+                        }
+                        None
+                    },
+                    Event::Key(key_event) => keymap.action(focus, key_event),
+                    // A click focuses a pane, and starts or stops the stopwatch or the timer
+                    Event::Mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, .. })
+                        if ringing.is_empty() && !show_help && label_prompt.is_none() && report_panel.is_none() && alarm_panel.is_none() => {
+                        let clicked=pane_areas.iter().find(|(_, area)| area.contains(Position::new(column, row)));
+                        clicked.and_then(|(pane, _)| {
+                            focus = *pane;
+                            (focus==Pane::Stopwatch).then_some(Action::StartStop)
+                        })
+                    },
+                    _ => None,
+                };
+                match action {
+                    Some(Action::Quit) => break,// we absolutely must add a break condition to the infinite loop in order to disable the raw mode later
+                    Some(Action::Help) => show_help=true,
+                    Some(Action::FocusNext) => focus=focus.next(),
+                    Some(Action::FocusPrevious) => focus=focus.previous(),
+                    Some(Action::Alarms) => alarm_panel=Some(AlarmPanel::new()),
+                    Some(Action::StartStop) if show_timer => timer.next_state(),
+                    // The time measured is asked a label before the reset clears it
+                    Some(Action::StartStop) => {
+                        if let Some(elapsed)=stopwatch.next_state() {
+                            label_prompt=Some(LabelPrompt::new(elapsed));
+                        }
+                    },
+                    Some(Action::Pause) if show_timer => timer.toggle_pause(),
+                    Some(Action::Pause) => stopwatch.toggle_pause(),
+                    // Laps are only recorded on the stopwatch when it is shown and focused, even if the key is bound
+                    // globally. The table follows the new lap at its bottom.
+                    Some(Action::Lap) if !show_timer && focus==Pane::Stopwatch => {
+                        if stopwatch.lap() {
+                            lap_table_state.select(Some(stopwatch.laps().len()-1));
+                        }
+                    },
+                    Some(Action::ToggleTimer) => show_timer = !show_timer,
+                    Some(Action::TimerMode) => timer.toggle_mode(),
+                    Some(Action::AddMinute) => timer.adjust_countdown(1),
+                    Some(Action::RemoveMinute) => timer.adjust_countdown(-1),
+                    Some(Action::Report) => report_panel=Some(ReportPanel::new(&session_log)),
+                    Some(Action::ScrollUp) => lap_table_state.select_previous(),
+                    Some(Action::ScrollDown) => lap_table_state.select_next(),
+                    Some(Action::NextLocation) => selected_location=(selected_location+1)%weather_apps.len().max(1),
+                    Some(Action::PreviousLocation) => {
+                        selected_location=(selected_location+weather_apps.len().max(1)-1)%weather_apps.len().max(1);
+                    },
+                    Some(Action::NextVariable) => variable=variable.next(),
+                    Some(Action::NextOverlay) => overlay=overlay.next(),
+                    Some(Action::Lap) | None => {},
+                }
+                true
+            },
+            AppEvent::Weather(update) => {
+                weather_apps[update.index].update(update.result);
+                true
+            },
+            AppEvent::Tick => false,
+        };
+        // Ring the terminal bell and bring the timer to the front when its time is up
+        if timer.update() {
+            execute!(&stdout, Print("\x07"))?;
+            show_timer=true;
+            redraw=true;
+        }
+        // The alarms are set to the minute, checking them once per second is enough
+        let now=Utc::now();
+        if alarms_checked!=Some(now.timestamp()) {
+            alarms_checked=Some(now.timestamp());
+            let (due, saved)=alarms.due(now);
+            if let Err(error)=saved {
+                ringing_error=Some(save_error(error));
             }
+            if !due.is_empty() {
+                execute!(&stdout, Print("\x07"))?;
+                ringing.extend(due);
+                redraw=true;
+            }
+        }
+        let frame=frame_key(&stopwatch, &timer);
+        if !redraw && drawn.as_ref()==Some(&frame) {
+            continue;
         }
+        drawn=Some(frame);
+        let help_hint=format!(" ({}: help, {}: next pane)", keymap.key_for(Action::Help), keymap.key_for(Action::FocusNext));
+        let title=|name: &str, pane: Pane| if focus==pane {format!("{name}{help_hint}")} else {name.to_string()};
         terminal.draw( |f| {
             let layout=Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(f.area());
-        let stopwatch_area=layout[0];
-        let time_area=layout[1];
-        // Split once again the time_area frame. I chose London as required then Kyiv and New York city.
-        let split_time_area=Layout::default().direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(10), // Allocate space for the first paragraph
-            Constraint::Percentage(70), // Allocate space for the second paragraph
-            Constraint::Percentage(10), // Allocate space for the third paragraph
-            Constraint::Percentage(10), // Allocate space for the fourth paragraph
-        ])
-        .split(time_area);
-        let london_time_area=split_time_area[0];
-        let london_chart_area=split_time_area[1];
-        let kyiv_time_area=split_time_area[2];
-        let nyork_time_area=split_time_area[3];    
+        let stopwatch_split=Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(layout[0]);
+        let stopwatch_area=stopwatch_split[0];
+        let laps_area=stopwatch_split[1];
+        // The clocks take the top of the right half and the temperature chart what is left below them
+        let (clock_areas, weather_area)=clock_areas(layout[1], clocks.len());
+        // One tab per location above the chart, and the daily minimum and maximum temperatures below it. A banner
+        // line on top tells when the forecast is out of date.
+        let weather_app=&weather_apps[selected_location];
+        let banner=weather_app.offline_banner(refresh_interval);
+        let weather_split=Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length(banner.is_some() as u16), Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+        .split(weather_area);
 
-        let stopwatch_block=block_with("Stopwatch");
-        let utc_time_block=block_with("London Time");
-        let london_temp_block=block_with("London Temperature");
-        let kyiv_time_block=block_with("Kyiv Time");
-        let nyork_time_block=block_with("New York Time");  
+        pane_areas=vec![(Pane::Stopwatch, layout[0]), (Pane::Chart, weather_area)];
+        pane_areas.extend(clock_areas.iter().map(|area| (Pane::Clocks, *area)));
+        let stopwatch_title=title(if stopwatch.is_paused() {"Stopwatch (paused)"} else {"Stopwatch"}, Pane::Stopwatch);
+        let stopwatch_block=pane_block(&stopwatch_title, focus==Pane::Stopwatch);
+        let chart_title=title(&match overlay {
+            Overlay::None => format!("{} {}", weather_app.name, variable.name()),
+            Overlay::FeelsLike => format!("{} temperature and feels like", weather_app.name),
+            Overlay::Locations => format!("{} everywhere", variable.name()),
+        }, Pane::Chart);
+        let (series, y_title)=chart_series(&weather_apps, selected_location, variable, overlay);
+        let marker=now_marker(&series);
+        let has_data=series.iter().any(|series| !series.data.is_empty());
+        let chart=forecast_chart(&series, &marker, y_title, pane_block(&chart_title, focus==Pane::Chart));
 
         let stopwatch_text=Paragraph::new(stopwatch.get_time()).block(stopwatch_block);
-        let london_time_text=Paragraph::new(time_pretty(0)).block(utc_time_block);
-        let kyiv_time_text=Paragraph::new(time_pretty(5)).block(kyiv_time_block);
-        let nyork_time_text=Paragraph::new(time_pretty(-5)).block(nyork_time_block);
 
-        let dataset=vec![Dataset::default()
-        .name("Time vs Temperature")
-        .marker(symbols::Marker::Dot)
-        .graph_type(GraphType::Line)
-        .style(Style::default().cyan())
-        .data(&data)
-        ];
-        
-        let x_labels=app_london.get_x_labels();
-        let y_labels=app_london.get_y_labels();
-        let x_axis=Axis::default()
-        .title("Time".red())
-        .style(Style::default().white())
-        .bounds([x_min, x_max])
-        .labels(x_labels);
-
-        let y_axis=Axis::default()
-        .title("Temperature (°C)".green())
-        .style(Style::default().white())
-        .bounds([y_min, y_max])
-        .labels(y_labels);
-
-        let chart=Chart::new(dataset)
-        .block(london_temp_block)
-        .x_axis(x_axis)
-        .y_axis(y_axis);
-
-        f.render_widget(stopwatch_text, stopwatch_area);
-        f.render_widget(london_time_text, london_time_area);
-        f.render_widget(chart, london_chart_area);
-        f.render_widget(kyiv_time_text, kyiv_time_area);
-        f.render_widget(nyork_time_text, nyork_time_area);
+        if show_timer {
+            let timer_title=title(match timer.mode() {TimerMode::Countdown => "Countdown", TimerMode::Pomodoro => "Pomodoro"}, Pane::Stopwatch);
+            let start_key=keymap.key_for(Action::StartStop);
+            f.render_widget(timer_panel(&timer, pane_block(&timer_title, focus==Pane::Stopwatch), &start_key), layout[0]);
+        } else {
+            f.render_widget(stopwatch_text, stopwatch_area);
+            let laps=lap_table(&stopwatch, pane_block("Laps", focus==Pane::Stopwatch));
+            f.render_stateful_widget(laps, laps_area, &mut lap_table_state);
+        }
+        let tabs=Tabs::new(weather_apps.iter().map(|weather_app| weather_app.name.clone()))
+        .select(selected_location)
+        .highlight_style(Style::default().reversed())
+        .block(pane_block("Weather", focus==Pane::Chart));
+        let daily=Paragraph::new(weather_app.daily_summary().join("  ")).block(pane_block("Daily min/max", focus==Pane::Chart));
+        if let Some(banner)=banner {
+            f.render_widget(Paragraph::new(banner).white().on_red(), weather_split[0]);
+        }
+        f.render_widget(tabs, weather_split[1]);
+        if has_data {
+            f.render_widget(chart, weather_split[2]);
+        } else {
+            let message=weather_app.last_error.as_deref().unwrap_or("Waiting for the forecast...");
+            f.render_widget(Paragraph::new(message).block(pane_block(&chart_title, focus==Pane::Chart)), weather_split[2]);
+        }
+        f.render_widget(daily, weather_split[3]);
+        let now=Utc::now();
+        for (index, (clock, area)) in clocks.iter().zip(clock_areas).enumerate() {
+            let name=format!("{} Time", clock.name);
+            let title=if index==0 {title(&name, Pane::Clocks)} else {name};
+            // The next alarm set in the zone of the clock
+            let next_alarm=alarms.list().iter()
+                .filter(|alarm| alarm.zone==clock.zone().name())
+                .filter_map(|alarm| Some((alarm.next_after(now)?, alarm)))
+                .min_by_key(|(time, _)| *time);
+            let text=match next_alarm {
+                Some((_, alarm)) => format!("{}  Alarm {} {}", clock.time_pretty(), alarm.time, alarm.label),
+                None => clock.time_pretty(),
+            };
+            f.render_widget(Paragraph::new(text).block(pane_block(&title, focus==Pane::Clocks)), area);
+        }
+        if let Some(panel)=&mut alarm_panel {
+            panel.render(f, &alarms, &clocks);
+        }
+        if let Some(panel)=&mut report_panel {
+            panel.render(f);
+        }
+        if let Some(prompt)=&label_prompt {
+            prompt.render(f);
+        }
+        if show_help {
+            render_help(f, &keymap);
+        }
+        if !ringing.is_empty() {
+            render_alert(f, &ringing, &clocks, ringing_error.as_deref());
+        }
 
         })?;
-        //The terminal is going to loop as fast as it possibly can, so let’s put it to sleep each
-        //time to keep the screen from flickering. Using .sleep() can be a bad idea in complex and async code,
-        //but we are just running a little terminal app on a single thread.
-        sleep(std::time::Duration::from_millis(10));
-        // Ratatui has a convenience method called .clear(), so we don’t need to use a crossterm
-        //command to clear the screen anymore.
-        terminal.clear()?;
     }
-    disable_raw_mode()?;
-    execute!(&stdout, event::DisableFocusChange)?;
+    drop(guard);
+    if stopwatch.export_laps(&cli.laps_csv)? {
+        println!("Laps exported to {}", cli.laps_csv.display());
+    }
     Ok(())
 }
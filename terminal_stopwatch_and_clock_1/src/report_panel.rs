@@ -0,0 +1,197 @@
+// Prompt for the label of a timing once the stopwatch is stopped, and the popup of the time report built from the
+// session log. The days and weeks of the report are the local ones.
+use chrono::{Local, Utc};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Constraint,
+    style::{Style, Stylize},
+    widgets::{Clear, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use std::{path::Path, time::Duration};
+
+use crate::{
+    block_with,
+    popup_area,
+    session_log::{export_report, report, Period, ReportRow, Session, SessionLog},
+    stopwatch::{format_elapsed, Precision},
+};
+
+// The timing waits here for its label, it is logged once Enter or Esc is pressed
+pub struct LabelPrompt {
+    elapsed: Duration,
+    label: String,
+    error: Option<String>, // The timing couldn't be logged, it can be tried again or given up
+}
+
+impl LabelPrompt {
+    pub fn new(elapsed: Duration) -> Self {
+        Self { elapsed, label: String::new(), error: None }
+    }
+
+    // Returns false once the timing is logged. Esc logs it without a label, or gives it up once logging it failed.
+    pub fn handle_key(&mut self, code: KeyCode, log: &SessionLog) -> bool {
+        let label = match code {
+            KeyCode::Enter => self.label.clone(),
+            KeyCode::Esc if self.error.is_some() => return false,
+            KeyCode::Esc => String::new(),
+            KeyCode::Char(c) => {
+                self.label.push(c);
+                return true;
+            },
+            KeyCode::Backspace => {
+                self.label.pop();
+                return true;
+            },
+            _ => return true,
+        };
+        // A failed append is shown rather than closing the dashboard
+        match log.append(&Session::new(Utc::now(), self.elapsed, &label)) {
+            Ok(()) => false,
+            Err(error) => {
+                self.error = Some(format!("Logging failed: {error}"));
+                true
+            },
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame) {
+        let area = popup_area(f.area(), 60, 4 + self.error.is_some() as u16);
+        f.render_widget(Clear, area);
+        let mut text = format!("Timed {}\nLabel: {}", format_elapsed(self.elapsed, Precision::Seconds), self.label);
+        let title = match &self.error {
+            Some(error) => {
+                text.push_str(&format!("\n{error}"));
+                "Log the timing (Enter: try again, Esc: give up)"
+            },
+            None => "Log the timing (Enter: save, Esc: no label)",
+        };
+        f.render_widget(Paragraph::new(text).block(block_with(title)), area);
+    }
+}
+
+pub struct ReportPanel {
+    period: Period,
+    rows: Vec<ReportRow>,
+    table_state: TableState,
+    message: Option<String>, // Result of the last export
+    load_error: Option<String>, // The log couldn't be read, it is shown instead of the report
+}
+
+impl ReportPanel {
+    pub fn new(log: &SessionLog) -> Self {
+        let mut panel = Self {
+            period: Period::Day,
+            rows: Vec::new(),
+            table_state: TableState::default(),
+            message: None,
+            load_error: None,
+        };
+        panel.reload(log);
+        panel
+    }
+
+    fn reload(&mut self, log: &SessionLog) {
+        (self.rows, self.load_error) = match log.load() {
+            Ok(sessions) => (report(&sessions, self.period, &Local), None),
+            Err(error) => (Vec::new(), Some(format!("The log could not be read: {error}"))),
+        };
+        self.table_state.select((!self.rows.is_empty()).then_some(0));
+    }
+
+    // Returns false once the panel is closed, by Esc or by the key bound to the report when `closes` is set
+    pub fn handle_key(&mut self, code: KeyCode, closes: bool, log: &SessionLog, csv_path: &Path) -> bool {
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Up => self.table_state.select_previous(),
+            KeyCode::Down => self.table_state.select_next(),
+            KeyCode::Char('w') => {
+                self.period = self.period.next();
+                self.reload(log);
+            },
+            KeyCode::Char('e') => {
+                // A failed export is shown rather than closing the dashboard
+                self.message = Some(match export_report(&self.rows, self.period, csv_path) {
+                    Ok(()) => format!("Exported to {}", csv_path.display()),
+                    Err(error) => format!("Export to {} failed: {error}", csv_path.display()),
+                });
+            },
+            _ if closes => return false,
+            _ => {},
+        }
+        true
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let area = popup_area(f.area(), 70, 20);
+        f.render_widget(Clear, area);
+        let title = format!("Time per {} (w: day/week, e: export CSV, Esc: close)", self.period.name());
+        if self.rows.is_empty() {
+            let text = self.load_error.as_deref().unwrap_or("Nothing logged yet: stop the stopwatch to log a timing");
+            f.render_widget(Paragraph::new(text).block(block_with(&title)), area);
+            return;
+        }
+        let rows: Vec<Row> = self.rows.iter()
+            .map(|row| Row::new(vec![
+                row.period.clone(),
+                row.label.clone(),
+                row.sessions.to_string(),
+                format_elapsed(row.total, Precision::Seconds),
+            ]))
+            .collect();
+        let mut table = Table::new(rows, [Constraint::Length(11), Constraint::Min(0), Constraint::Length(8), Constraint::Length(10)])
+            .header(Row::new(vec!["Period", "Label", "Timings", "Total"]).bold())
+            .row_highlight_style(Style::default().reversed())
+            .block(block_with(&title));
+        if let Some(message) = &self.message {
+            table = table.footer(Row::new(vec![String::new(), message.clone()]));
+        }
+        f.render_stateful_widget(table, area, &mut self.table_state);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::{read_to_string, remove_file, write}};
+
+    #[test]
+    fn failed_logging_keeps_the_prompt_open() {
+        let log = SessionLog::new(&temp_dir().join(format!("no_such_dir_{}", std::process::id())).join("sessions.jsonl"));
+        let mut prompt = LabelPrompt::new(Duration::from_secs(90));
+        prompt.handle_key(KeyCode::Char('a'), &log);
+        assert!(prompt.handle_key(KeyCode::Enter, &log));
+        assert!(prompt.error.as_deref().is_some_and(|error| error.starts_with("Logging failed")));
+        // Esc gives the timing up once it couldn't be logged
+        assert!(!prompt.handle_key(KeyCode::Esc, &log));
+    }
+
+    #[test]
+    fn prompt_logs_the_label() {
+        let path = temp_dir().join(format!("prompt_sessions_{}.jsonl", std::process::id()));
+        let log = SessionLog::new(&path);
+        let mut prompt = LabelPrompt::new(Duration::from_secs(90));
+        prompt.handle_key(KeyCode::Char('a'), &log);
+        assert!(!prompt.handle_key(KeyCode::Enter, &log));
+        let content = read_to_string(&path).unwrap();
+        remove_file(&path).unwrap();
+        assert!(content.contains("\"label\":\"a\""));
+    }
+
+    #[test]
+    fn unreadable_log_is_shown_in_the_report() {
+        let path = temp_dir().join(format!("invalid_sessions_{}.jsonl", std::process::id()));
+        write(&path, "not json\n").unwrap();
+        let log = SessionLog::new(&path);
+        let mut panel = ReportPanel::new(&log);
+        assert!(panel.rows.is_empty());
+        assert!(panel.load_error.is_some());
+        assert!(panel.handle_key(KeyCode::Char('w'), false, &log, &path));
+        assert!(panel.load_error.is_some());
+        // The key of the report closes it, whatever it is bound to
+        assert!(panel.handle_key(KeyCode::Char('r'), false, &log, &path));
+        assert!(!panel.handle_key(KeyCode::F(2), true, &log, &path));
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+// Log of the timings of the stopwatch, which makes it a small time tracker. Each stopped timing is appended to a
+// file as a json line, with the label typed by the user, and the report sums the time per label per day or week.
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::{read_to_string, write, OpenOptions}, io::{self, Write}, path::{Path, PathBuf},
+    time::Duration};
+
+pub const SESSIONS_FILE: &str = "sessions.jsonl";
+pub const NO_LABEL: &str = "(no label)";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub ended_at: i64, // Unix timestamp
+    pub seconds: f64,
+    #[serde(default)]
+    pub label: String,
+}
+
+impl Session {
+    pub fn new(ended_at: DateTime<Utc>, elapsed: Duration, label: &str) -> Self {
+        Self { ended_at: ended_at.timestamp(), seconds: elapsed.as_secs_f64(), label: label.trim().to_string() }
+    }
+}
+
+pub struct SessionLog {
+    path: PathBuf,
+}
+
+impl SessionLog {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf() }
+    }
+
+    pub fn append(&self, session: &Session) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(session).map_err(io::Error::other)?)
+    }
+
+    // A missing log has no session, while an invalid line is reported
+    pub fn load(&self) -> io::Result<Vec<Session>> {
+        let content = match read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+        content.lines().enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| serde_json::from_str(line).map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{} line {}: {error}", self.path.display(), index + 1))
+            }))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+impl Period {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Period::Day => Period::Week,
+            Period::Week => Period::Day,
+        }
+    }
+
+    // "2025-01-20" for a day, and the ISO week "2025-W04" for a week
+    fn key(&self, date: NaiveDate) -> String {
+        match self {
+            Period::Day => date.format("%Y-%m-%d").to_string(),
+            Period::Week => {
+                let week = date.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportRow {
+    pub period: String,
+    pub label: String,
+    pub sessions: usize,
+    pub total: Duration,
+}
+
+// Total time per label and period, the latest period first. The days and weeks are those of the given zone.
+pub fn report<Z: TimeZone>(sessions: &[Session], period: Period, zone: &Z) -> Vec<ReportRow> {
+    let mut totals: BTreeMap<(String, String), (usize, f64)> = BTreeMap::new();
+    for session in sessions {
+        let Some(ended_at) = DateTime::from_timestamp(session.ended_at, 0) else {
+            continue;
+        };
+        let label = if session.label.is_empty() {NO_LABEL.to_string()} else {session.label.clone()};
+        let total = totals.entry((period.key(ended_at.with_timezone(zone).date_naive()), label)).or_default();
+        total.0 += 1;
+        total.1 += session.seconds;
+    }
+    let mut rows: Vec<ReportRow> = totals.into_iter()
+        .map(|((period, label), (sessions, seconds))| ReportRow {
+            period,
+            label,
+            sessions,
+            total: Duration::from_secs_f64(seconds.max(0.0)),
+        })
+        .collect();
+    rows.sort_by(|a, b| b.period.cmp(&a.period).then_with(|| a.label.cmp(&b.label)));
+    rows
+}
+
+// Write the report as CSV, the times being in seconds
+pub fn export_report(rows: &[ReportRow], period: Period, path: &Path) -> io::Result<()> {
+    let mut csv = format!("{},label,sessions,seconds\n", period.name());
+    for row in rows {
+        let label = if row.label.contains([',', '"']) {format!("\"{}\"", row.label.replace('"', "\"\""))} else {row.label.clone()};
+        csv.push_str(&format!("{},{},{},{:.3}\n", row.period, label, row.sessions, row.total.as_secs_f64()));
+    }
+    write(path, csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn session(time: &str, seconds: f64, label: &str) -> Session {
+        let ended_at = DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc);
+        Session::new(ended_at, Duration::from_secs_f64(seconds), label)
+    }
+
+    fn sessions() -> Vec<Session> {
+        vec![
+            session("2025-01-19T10:00:00Z", 600.0, "review"), // Sunday, in the week 3
+            session("2025-01-20T09:00:00Z", 1800.0, "review"),
+            session("2025-01-20T11:00:00Z", 900.0, " coding "),
+            session("2025-01-20T15:00:00Z", 300.0, "review"),
+            session("2025-01-21T09:00:00Z", 60.0, ""),
+        ]
+    }
+
+    #[test]
+    fn sums_per_label_per_day() {
+        let rows = report(&sessions(), Period::Day, &Utc);
+        let summary: Vec<(&str, &str, usize, u64)> = rows.iter()
+            .map(|row| (row.period.as_str(), row.label.as_str(), row.sessions, row.total.as_secs()))
+            .collect();
+        assert_eq!(summary, [
+            ("2025-01-21", NO_LABEL, 1, 60),
+            ("2025-01-20", "coding", 1, 900),
+            ("2025-01-20", "review", 2, 2100),
+            ("2025-01-19", "review", 1, 600),
+        ]);
+    }
+
+    #[test]
+    fn sums_per_label_per_week() {
+        let rows = report(&sessions(), Period::Week, &Utc);
+        let summary: Vec<(&str, &str, u64)> = rows.iter()
+            .map(|row| (row.period.as_str(), row.label.as_str(), row.total.as_secs()))
+            .collect();
+        assert_eq!(summary, [
+            ("2025-W04", NO_LABEL, 60),
+            ("2025-W04", "coding", 900),
+            ("2025-W04", "review", 2100),
+            ("2025-W03", "review", 600),
+        ]);
+    }
+
+    #[test]
+    fn the_days_are_those_of_the_zone() {
+        // 23:30 UTC is already the next day in Kyiv
+        let sessions = [session("2025-01-20T23:30:00Z", 60.0, "late")];
+        assert_eq!(report(&sessions, Period::Day, &Utc)[0].period, "2025-01-20");
+        assert_eq!(report(&sessions, Period::Day, &chrono_tz::Europe::Kyiv)[0].period, "2025-01-21");
+    }
+
+    #[test]
+    fn appends_and_loads_the_log() {
+        let path = temp_dir().join(format!("sessions_{}.jsonl", std::process::id()));
+        let log = SessionLog::new(&path);
+        assert!(log.load().unwrap().is_empty());
+        for session in sessions() {
+            log.append(&session).unwrap();
+        }
+        assert_eq!(log.load().unwrap(), sessions());
+        std::fs::write(&path, "{\"ended_at\": 0, \"seconds\": 1}\nnot json\n").unwrap();
+        let error = log.load().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(" line 2: "), "{error}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn exports_the_report() {
+        let path = temp_dir().join(format!("report_{}.csv", std::process::id()));
+        export_report(&report(&sessions()[..2], Period::Day, &Utc), Period::Day, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "day,label,sessions,seconds\n2025-01-20,review,1,1800.000\n2025-01-19,review,1,600.000\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,456 @@
+// The stopwatch of the left panel. Enter starts, stops then resets it, the pause key holds it without stopping it
+// and the lap key records the time of a lap while it runs.
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{fs::write, io, path::Path, time::{Duration, Instant}};
+
+// Where the stopwatch reads the time from. The tests use a clock they move forward by hand.
+pub trait TimeSource {
+    fn now(&self) -> Instant;
+}
+
+pub struct MonotonicClock;
+
+impl TimeSource for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Number of digits shown after the seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    Seconds,
+    Tenths,
+    #[default]
+    Centiseconds,
+    Milliseconds,
+}
+
+impl Precision {
+    // Time between two changes of the last digit
+    pub fn step(&self) -> Duration {
+        match self {
+            Precision::Seconds => Duration::from_secs(1),
+            Precision::Tenths => Duration::from_millis(100),
+            Precision::Centiseconds => Duration::from_millis(10),
+            Precision::Milliseconds => Duration::from_millis(1),
+        }
+    }
+}
+
+pub struct Stopwatch<T: TimeSource = MonotonicClock> {
+    clock: T,
+    state: StopwatchState,
+    precision: Precision,
+    elapsed: Duration,    // Time counted until the last pause or stop, the running time is added on top of it
+    splits: Vec<Duration>,// Total time elapsed at the end of each lap
+    previous_runs: Vec<Vec<Duration>>, // Splits of the runs before the last reset, kept until they are exported
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopwatchState {
+    NotStarted,
+    Running { since: Instant },
+    Paused,      // held by the user
+    FocusPaused, // held while the terminal doesn't have the focus, it runs again once the focus comes back
+    Done,
+}
+
+// A row of the lap table
+pub struct Lap {
+    pub number: usize,
+    pub time: Duration,  // duration of the lap itself
+    pub total: Duration, // time elapsed since the start of the stopwatch
+}
+
+// Show at least two digits for the minutes and the seconds. The hours only show up after the first hour.
+pub fn format_elapsed(elapsed: Duration, precision: Precision) -> String {
+    let mut elapsed=elapsed.as_millis();
+    let hours=elapsed / 3_600_000;
+    elapsed-=hours*3_600_000;
+    let minutes=elapsed / 60000;
+    elapsed-=minutes*60000;
+    let seconds = elapsed/1000;
+    elapsed-=seconds*1000;
+    let fraction=match precision {
+        Precision::Seconds => String::new(),
+        Precision::Tenths => format!(".{}", elapsed/100),
+        Precision::Centiseconds => format!(".{:02}", elapsed/10),
+        Precision::Milliseconds => format!(".{:03}", elapsed),
+    };
+    if hours > 0 {
+        format!("{hours}:{:02}:{:02}{fraction}",minutes,seconds)
+    } else {
+        format!("{:02}:{:02}{fraction}",minutes,seconds)
+    }
+}
+
+// The laps ending at the given splits
+fn laps(splits: &[Duration]) -> Vec<Lap> {
+    let mut previous=Duration::ZERO;
+    splits.iter().enumerate().map(|(index, &total)| {
+        let lap=Lap { number: index+1, time: total-previous, total };
+        previous=total;
+        lap
+    }).collect()
+}
+
+impl Stopwatch {
+    pub fn new(precision: Precision)->Self {
+        Self::with_clock(MonotonicClock, precision)
+    }
+}
+
+impl<T: TimeSource> Stopwatch<T> {
+    pub fn with_clock(clock: T, precision: Precision) -> Self {
+        Self {
+            clock,
+            state: StopwatchState::NotStarted,
+            precision,
+            elapsed: Duration::ZERO,
+            splits: Vec::new(),
+            previous_runs: Vec::new(),
+        }
+    }
+    // The time never goes backwards, even if the clock did
+    pub fn elapsed(&self) -> Duration {
+        match self.state {
+            StopwatchState::Running { since } => self.elapsed + self.clock.now().saturating_duration_since(since),
+            _ => self.elapsed,
+        }
+    }
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+    pub fn get_time(&self) -> String {
+        format_elapsed(self.elapsed(), self.precision)
+    }
+    // Returns the measured time when the stopwatch is stopped, so that it can be logged before the reset
+    pub fn next_state(&mut self) -> Option<Duration> {
+        use StopwatchState::*;
+        match self.state {
+            NotStarted => self.run(),
+            Running {..} | Paused | FocusPaused => {
+                self.hold();
+                self.state=Done;
+                return Some(self.elapsed);
+            },
+            Done => {
+                self.state=NotStarted;
+                self.elapsed = Duration::ZERO;
+                if !self.splits.is_empty() {
+                    self.previous_runs.push(std::mem::take(&mut self.splits));
+                }
+            },
+        }
+        None
+    }
+    fn run(&mut self) {
+        self.state=StopwatchState::Running { since: self.clock.now() };
+    }
+    // Add the running time to the time counted so far
+    fn hold(&mut self) {
+        self.elapsed=self.elapsed();
+    }
+    // Hold or release the stopwatch on the user's request
+    pub fn toggle_pause(&mut self) {
+        match self.state {
+            StopwatchState::Running {..} => {
+                self.hold();
+                self.state=StopwatchState::Paused;
+            },
+            StopwatchState::Paused => self.run(),
+            _ => {},
+        }
+    }
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state, StopwatchState::Paused)
+    }
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, StopwatchState::Running {..})
+    }
+    // Laps can only be recorded while the stopwatch runs
+    pub fn lap(&mut self) -> bool {
+        if let StopwatchState::Running {..} = self.state {
+            self.splits.push(self.elapsed());
+            true
+        } else {false}
+    }
+    pub fn laps(&self) -> Vec<Lap> {
+        laps(&self.splits)
+    }
+    // Numbers of the best and worst laps, once there are at least two laps to compare
+    pub fn best_and_worst_laps(&self) -> Option<(usize, usize)> {
+        let laps=self.laps();
+        if laps.len() < 2 {
+            return None;
+        }
+        let best=laps.iter().min_by_key(|lap| lap.time)?.number;
+        let worst=laps.iter().max_by_key(|lap| lap.time)?.number;
+        Some((best, worst))
+    }
+    // Write the laps of every run as CSV, the runs reset before included, the times being in seconds. Nothing is
+    // written when no lap was recorded.
+    pub fn export_laps(&self, path: &Path) -> io::Result<bool> {
+        let runs: Vec<&Vec<Duration>>=self.previous_runs.iter().chain([&self.splits]).filter(|splits| !splits.is_empty()).collect();
+        if runs.is_empty() {
+            return Ok(false);
+        }
+        let mut csv=String::from("run,lap,lap_seconds,total_seconds\n");
+        for (run, splits) in runs.into_iter().enumerate() {
+            for lap in laps(splits) {
+                csv.push_str(&format!("{},{},{:.3},{:.3}\n", run+1, lap.number, lap.time.as_secs_f64(), lap.total.as_secs_f64()));
+            }
+        }
+        write(path, csv)?;
+        Ok(true)
+    }
+    //pause method to pause the stopwatch when the focus is lost
+    pub fn pause(&mut self) {
+        if let StopwatchState::Running {..} = self.state {
+            self.hold();
+            self.state=StopwatchState::FocusPaused;
+        }
+    }
+    //resume method to resume the stopwatch count when the focus is gained
+    pub fn resume(&mut self) {
+        if let StopwatchState::FocusPaused = self.state {
+            self.run();
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    // A clock which only moves when the test says so, shared with the tests of the timer
+    #[derive(Clone)]
+    pub(crate) struct ManualClock {
+        start: Instant,
+        offset: Rc<Cell<Duration>>,
+    }
+
+    impl ManualClock {
+        pub(crate) fn new() -> Self {
+            Self { start: Instant::now(), offset: Rc::new(Cell::new(Duration::ZERO)) }
+        }
+        pub(crate) fn advance(&self, millis: u64) {
+            self.offset.set(self.offset.get() + Duration::from_millis(millis));
+        }
+    }
+
+    impl TimeSource for ManualClock {
+        fn now(&self) -> Instant {
+            self.start + self.offset.get()
+        }
+    }
+
+    fn stopwatch() -> (Stopwatch<ManualClock>, ManualClock) {
+        let clock = ManualClock::new();
+        (Stopwatch::with_clock(clock.clone(), Precision::Centiseconds), clock)
+    }
+
+    #[test]
+    fn counts_while_running_only() {
+        let (mut stopwatch, clock) = stopwatch();
+        clock.advance(5000);
+        assert_eq!(stopwatch.get_time(), "00:00.00");
+        stopwatch.next_state();
+        clock.advance(1234);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(1234));
+        assert_eq!(stopwatch.get_time(), "00:01.23");
+    }
+
+    #[test]
+    fn stop_freezes_then_reset_clears() {
+        let (mut stopwatch, clock) = stopwatch();
+        assert_eq!(stopwatch.next_state(), None);
+        clock.advance(2000);
+        assert_eq!(stopwatch.next_state(), Some(Duration::from_secs(2)));
+        clock.advance(3000);
+        assert_eq!(stopwatch.get_time(), "00:02.00");
+        stopwatch.next_state();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+        stopwatch.next_state();
+        clock.advance(500);
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn user_pause_holds_the_time() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        stopwatch.toggle_pause();
+        assert!(stopwatch.is_paused());
+        clock.advance(10_000);
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(1));
+        stopwatch.toggle_pause();
+        clock.advance(1000);
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn elapsed_is_correct_across_many_pauses() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        for _ in 0..100 {
+            clock.advance(10);
+            stopwatch.toggle_pause();
+            clock.advance(990);
+            stopwatch.toggle_pause();
+        }
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn enter_stops_a_stopwatch_paused_by_the_focus() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(3000);
+        stopwatch.pause();
+        clock.advance(3000);
+        // Enter used to restart the stopwatch from zero here
+        stopwatch.next_state();
+        assert_eq!(stopwatch.get_time(), "00:03.00");
+        stopwatch.resume();
+        clock.advance(3000);
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn focus_resumes_what_the_focus_paused() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        stopwatch.pause();
+        clock.advance(5000);
+        stopwatch.resume();
+        clock.advance(1000);
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn focus_does_not_resume_a_user_pause() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        stopwatch.toggle_pause();
+        stopwatch.pause();
+        stopwatch.resume();
+        clock.advance(1000);
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn focus_does_not_start_a_stopwatch() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.resume();
+        clock.advance(1000);
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn runs_until_paused_or_stopped() {
+        let (mut stopwatch, _) = stopwatch();
+        assert!(!stopwatch.is_running());
+        stopwatch.next_state();
+        assert!(stopwatch.is_running());
+        stopwatch.toggle_pause();
+        assert!(!stopwatch.is_running());
+        stopwatch.toggle_pause();
+        stopwatch.pause();
+        assert!(!stopwatch.is_running());
+        stopwatch.resume();
+        stopwatch.next_state();
+        assert!(!stopwatch.is_running());
+        assert_eq!(Precision::Tenths.step(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn hours_roll_over() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(59 * 60_000 + 59_990);
+        assert_eq!(stopwatch.get_time(), "59:59.99");
+        clock.advance(10);
+        assert_eq!(stopwatch.get_time(), "1:00:00.00");
+        clock.advance(25 * 3_600_000 + 61_000);
+        assert_eq!(stopwatch.get_time(), "26:01:01.00");
+    }
+
+    #[test]
+    fn precision_sets_the_fraction_digits() {
+        let elapsed = Duration::from_millis(83_456);
+        assert_eq!(format_elapsed(elapsed, Precision::Seconds), "01:23");
+        assert_eq!(format_elapsed(elapsed, Precision::Tenths), "01:23.4");
+        assert_eq!(format_elapsed(elapsed, Precision::Centiseconds), "01:23.45");
+        assert_eq!(format_elapsed(elapsed, Precision::Milliseconds), "01:23.456");
+        let (stopwatch, _) = stopwatch();
+        let stopwatch = Stopwatch { precision: Precision::Seconds, ..stopwatch };
+        assert_eq!(stopwatch.get_time(), "00:00");
+    }
+
+    #[test]
+    fn laps_ignore_the_paused_time() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        assert!(stopwatch.lap());
+        stopwatch.toggle_pause();
+        clock.advance(60_000);
+        assert!(!stopwatch.lap());
+        stopwatch.toggle_pause();
+        clock.advance(3000);
+        stopwatch.lap();
+        clock.advance(2000);
+        stopwatch.lap();
+        let laps: Vec<(u64, u64)> = stopwatch.laps().iter()
+            .map(|lap| (lap.time.as_millis() as u64, lap.total.as_millis() as u64))
+            .collect();
+        assert_eq!(laps, vec![(1000, 1000), (3000, 4000), (2000, 6000)]);
+        assert_eq!(stopwatch.best_and_worst_laps(), Some((1, 2)));
+    }
+
+    #[test]
+    fn laps_reset_before_the_export_are_kept() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        stopwatch.lap();
+        clock.advance(500);
+        stopwatch.lap();
+        stopwatch.next_state();
+        stopwatch.next_state();
+        assert!(stopwatch.laps().is_empty());
+        // A run without laps adds no rows
+        stopwatch.next_state();
+        stopwatch.next_state();
+        stopwatch.next_state();
+        stopwatch.next_state();
+        clock.advance(2000);
+        stopwatch.lap();
+        let path = std::env::temp_dir().join(format!("laps_{}.csv", std::process::id()));
+        assert!(stopwatch.export_laps(&path).unwrap());
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(csv, "run,lap,lap_seconds,total_seconds\n1,1,1.000,1.000\n1,2,0.500,1.500\n2,1,2.000,2.000\n");
+    }
+
+    #[test]
+    fn nothing_is_exported_without_laps() {
+        let (mut stopwatch, clock) = stopwatch();
+        stopwatch.next_state();
+        clock.advance(1000);
+        stopwatch.next_state();
+        stopwatch.next_state();
+        let path = std::env::temp_dir().join(format!("no_laps_{}.csv", std::process::id()));
+        assert!(!stopwatch.export_laps(&path).unwrap());
+        assert!(!path.exists());
+    }
+}
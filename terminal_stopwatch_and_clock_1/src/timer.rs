@@ -0,0 +1,337 @@
+// Countdown timer sharing the left panel with the stopwatch. It either counts down a single duration or goes
+// through the Pomodoro cycle: work sessions separated by short breaks, with a long break every few sessions.
+use std::time::{Duration, Instant};
+
+use crate::{config::PomodoroConfig, stopwatch::{MonotonicClock, TimeSource}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimerMode {
+    Countdown,
+    Pomodoro,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Countdown,
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Countdown => "Countdown",
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+enum TimerState {
+    Ready,
+    Running,
+    Paused,      // held by the user
+    FocusPaused, // held while the terminal doesn't have the focus, like the stopwatch
+    Finished,
+}
+
+pub struct Timer<T: TimeSource = MonotonicClock> {
+    clock: T,
+    mode: TimerMode,
+    countdown: Duration,
+    pomodoro: PomodoroConfig,
+    phase: Phase,
+    state: TimerState,
+    now: Instant,
+    paused_time: Duration, // Time counted before the last pause
+    finished_at: Option<Instant>,
+    // Number of phases of the Pomodoro cycle completed so far
+    work_sessions: usize,
+    short_breaks: usize,
+    long_breaks: usize,
+}
+
+impl Timer {
+    pub fn new(countdown: Duration, pomodoro: PomodoroConfig) -> Self {
+        Self::with_clock(MonotonicClock, countdown, pomodoro)
+    }
+}
+
+impl<T: TimeSource> Timer<T> {
+    pub fn with_clock(clock: T, countdown: Duration, pomodoro: PomodoroConfig) -> Self {
+        let now = clock.now();
+        Self {
+            clock,
+            mode: TimerMode::Countdown,
+            countdown,
+            pomodoro,
+            phase: Phase::Countdown,
+            state: TimerState::Ready,
+            now,
+            paused_time: Duration::ZERO,
+            finished_at: None,
+            work_sessions: 0,
+            short_breaks: 0,
+            long_breaks: 0,
+        }
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn counts(&self) -> (usize, usize, usize) {
+        (self.work_sessions, self.short_breaks, self.long_breaks)
+    }
+
+    fn duration(&self) -> Duration {
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        match self.phase {
+            Phase::Countdown => self.countdown,
+            Phase::Work => minutes(self.pomodoro.work_minutes),
+            Phase::ShortBreak => minutes(self.pomodoro.short_break_minutes),
+            Phase::LongBreak => minutes(self.pomodoro.long_break_minutes),
+        }
+    }
+
+    // Time since the timer was last started or released
+    fn running_time(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.now)
+    }
+
+    fn elapsed(&self) -> Duration {
+        match self.state {
+            TimerState::Running => self.running_time() + self.paused_time,
+            _ => self.paused_time,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration().saturating_sub(self.elapsed())
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, TimerState::Running)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state, TimerState::Paused)
+    }
+
+    // The block flashes twice a second once the time is up, until Enter is pressed
+    pub fn is_flashing(&self) -> bool {
+        self.finished_at.is_some_and(|finished_at| {
+            (self.clock.now().saturating_duration_since(finished_at).as_millis() / 500).is_multiple_of(2)
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, TimerState::Finished)
+    }
+
+    // Check whether the time is up. Returns true only once, when the current phase ends, to ring the bell.
+    pub fn update(&mut self) -> bool {
+        if !self.is_running() || !self.remaining().is_zero() {
+            return false;
+        }
+        self.paused_time = self.duration();
+        self.state = TimerState::Finished;
+        self.finished_at = Some(self.clock.now());
+        match self.phase {
+            Phase::Work => self.work_sessions += 1,
+            Phase::ShortBreak => self.short_breaks += 1,
+            Phase::LongBreak => self.long_breaks += 1,
+            Phase::Countdown => {},
+        }
+        true
+    }
+
+    // Enter starts the timer, stops it while it runs and goes on with the next phase once it is finished
+    pub fn next_state(&mut self) {
+        match self.state {
+            TimerState::Ready => self.start(),
+            TimerState::Running | TimerState::Paused | TimerState::FocusPaused => self.reset(),
+            TimerState::Finished => {
+                self.phase = self.next_phase();
+                self.reset();
+                if self.mode == TimerMode::Pomodoro {
+                    self.start();
+                }
+            },
+        }
+    }
+
+    fn next_phase(&self) -> Phase {
+        match self.phase {
+            Phase::Countdown => Phase::Countdown,
+            Phase::Work if self.work_sessions.is_multiple_of(self.pomodoro.long_break_every.max(1)) => Phase::LongBreak,
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        }
+    }
+
+    fn start(&mut self) {
+        self.now = self.clock.now();
+        self.state = TimerState::Running;
+    }
+
+    fn reset(&mut self) {
+        self.state = TimerState::Ready;
+        self.paused_time = Duration::ZERO;
+        self.finished_at = None;
+    }
+
+    // Switch between the single countdown and the Pomodoro cycle, which starts over from its first work session
+    pub fn toggle_mode(&mut self) {
+        if !matches!(self.state, TimerState::Ready) {
+            return;
+        }
+        (self.mode, self.phase) = match self.mode {
+            TimerMode::Countdown => (TimerMode::Pomodoro, Phase::Work),
+            TimerMode::Pomodoro => (TimerMode::Countdown, Phase::Countdown),
+        };
+        self.work_sessions = 0;
+        self.short_breaks = 0;
+        self.long_breaks = 0;
+    }
+
+    // Change the duration of the countdown by a number of minutes before starting it
+    pub fn adjust_countdown(&mut self, minutes: i64) {
+        if self.mode == TimerMode::Countdown && matches!(self.state, TimerState::Ready) {
+            let seconds = self.countdown.as_secs() as i64 + minutes * 60;
+            self.countdown = Duration::from_secs(seconds.max(60) as u64);
+        }
+    }
+
+    // Hold or release the timer on the user's request
+    pub fn toggle_pause(&mut self) {
+        match self.state {
+            TimerState::Running => {
+                self.paused_time += self.running_time();
+                self.state = TimerState::Paused;
+            },
+            TimerState::Paused => self.start(),
+            _ => {},
+        }
+    }
+
+    //pause method to pause the timer when the focus is lost
+    pub fn pause(&mut self) {
+        if let TimerState::Running = self.state {
+            self.paused_time += self.running_time();
+            self.state = TimerState::FocusPaused;
+        }
+    }
+
+    //resume method to resume the timer when the focus is gained
+    pub fn resume(&mut self) {
+        if let TimerState::FocusPaused = self.state {
+            self.start();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stopwatch::tests::ManualClock;
+
+    const MINUTE: u64 = 60_000;
+
+    fn timer() -> (Timer<ManualClock>, ManualClock) {
+        let clock = ManualClock::new();
+        let pomodoro = PomodoroConfig { work_minutes: 25, short_break_minutes: 5, long_break_minutes: 15, long_break_every: 2 };
+        (Timer::with_clock(clock.clone(), Duration::from_secs(90), pomodoro), clock)
+    }
+
+    // Let the current phase run to its end, then go on with the next one
+    fn finish_phase(timer: &mut Timer<ManualClock>, clock: &ManualClock) {
+        clock.advance(timer.remaining().as_millis() as u64);
+        assert!(timer.update());
+        timer.next_state();
+    }
+
+    #[test]
+    fn countdown_rings_once_when_the_time_is_up() {
+        let (mut timer, clock) = timer();
+        clock.advance(10_000);
+        assert_eq!(timer.remaining(), Duration::from_secs(90));
+        timer.next_state();
+        clock.advance(89_999);
+        assert!(!timer.update());
+        assert_eq!(timer.remaining(), Duration::from_millis(1));
+        clock.advance(1);
+        assert!(timer.update());
+        assert!(!timer.update());
+        assert!(timer.is_finished());
+        assert!(timer.is_flashing());
+        clock.advance(500);
+        assert!(!timer.is_flashing());
+        // Enter resets the countdown without starting it again
+        timer.next_state();
+        assert!(!timer.is_running());
+        assert_eq!(timer.remaining(), Duration::from_secs(90));
+        assert_eq!(timer.phase(), Phase::Countdown);
+    }
+
+    #[test]
+    fn pomodoro_takes_a_long_break_every_few_work_sessions() {
+        let (mut timer, clock) = timer();
+        timer.toggle_mode();
+        assert_eq!(timer.phase(), Phase::Work);
+        timer.next_state();
+        let mut phases = vec![timer.phase()];
+        for _ in 0..6 {
+            finish_phase(&mut timer, &clock);
+            assert!(timer.is_running());
+            phases.push(timer.phase());
+        }
+        assert_eq!(phases, vec![Phase::Work, Phase::ShortBreak, Phase::Work, Phase::LongBreak, Phase::Work,
+            Phase::ShortBreak, Phase::Work]);
+        assert_eq!(timer.counts(), (3, 2, 1));
+        assert_eq!(timer.remaining(), Duration::from_secs(25 * 60));
+    }
+
+    #[test]
+    fn paused_work_session_doesnt_count_down() {
+        let (mut timer, clock) = timer();
+        timer.toggle_mode();
+        timer.next_state();
+        clock.advance(10 * MINUTE);
+        timer.toggle_pause();
+        assert!(timer.is_paused());
+        clock.advance(60 * MINUTE);
+        assert!(!timer.update());
+        assert_eq!(timer.remaining(), Duration::from_secs(15 * 60));
+        timer.toggle_pause();
+        clock.advance(5 * MINUTE);
+        assert_eq!(timer.remaining(), Duration::from_secs(10 * 60));
+        // The focus pause holds the time as well and releases it when the focus is back
+        timer.pause();
+        clock.advance(30 * MINUTE);
+        timer.resume();
+        clock.advance(10 * MINUTE);
+        assert!(timer.update());
+        assert_eq!(timer.counts(), (1, 0, 0));
+    }
+
+    #[test]
+    fn countdown_can_only_be_adjusted_before_it_starts() {
+        let (mut timer, _) = timer();
+        timer.adjust_countdown(-5);
+        assert_eq!(timer.remaining(), Duration::from_secs(60));
+        timer.adjust_countdown(2);
+        assert_eq!(timer.remaining(), Duration::from_secs(180));
+        timer.next_state();
+        timer.adjust_countdown(10);
+        timer.toggle_mode();
+        assert_eq!(timer.remaining(), Duration::from_secs(180));
+        assert_eq!(timer.mode(), TimerMode::Countdown);
+    }
+}
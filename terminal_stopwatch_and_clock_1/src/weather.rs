@@ -0,0 +1,472 @@
+// Weather forecast of the configured locations, fetched from open-meteo. A location is given by its coordinates or
+// by its name, which is looked up with the open-meteo geocoding API before the first forecast. The forecasts are
+// refreshed by a background thread and the last successful ones are cached on disk, to be shown while offline.
+use chrono::{naive::{NaiveDate, NaiveDateTime}, DateTime, Local, Utc};
+use reqwest::{blocking::get, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::{read_to_string, write}, io, path::{Path, PathBuf},
+    sync::mpsc::Sender, thread, time::Duration};
+
+use crate::config::LocationConfig;
+
+pub const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+pub const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+pub const CACHE_FILE: &str = "weather_cache.json";
+// Variables asked for every hour and every day
+const HOURLY: &str = "temperature_2m,apparent_temperature,precipitation,wind_speed_10m,relative_humidity_2m";
+const DAILY: &str = "temperature_2m_max,temperature_2m_min";
+
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+pub struct WeatherForecast {
+    latitude: f64,
+    longitude: f64,
+    timezone: String,
+    #[serde(default)]
+    utc_offset_seconds: i64, // The hourly times are in the local time of the location
+    hourly: HourlyData,
+    #[serde(default)]
+    daily: DailyData,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct HourlyData {
+    time: Vec<String>,          // Vec of Strings for time
+    temperature_2m: Vec<f64>,   // Vec of f64 for temperatures
+    apparent_temperature: Vec<f64>, // °C, felt with the wind and the humidity
+    precipitation: Vec<f64>,    // mm
+    wind_speed_10m: Vec<f64>,   // km/h
+    relative_humidity_2m: Vec<f64>, // %
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct DailyData {
+    time: Vec<String>,
+    temperature_2m_max: Vec<f64>,
+    temperature_2m_min: Vec<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResult {
+    latitude: f64,
+    longitude: f64,
+}
+
+// The hourly variable shown on the chart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeatherVariable {
+    Temperature,
+    ApparentTemperature,
+    Precipitation,
+    WindSpeed,
+    Humidity,
+}
+
+impl WeatherVariable {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherVariable::Temperature => "Temperature",
+            WeatherVariable::ApparentTemperature => "Feels like",
+            WeatherVariable::Precipitation => "Precipitation",
+            WeatherVariable::WindSpeed => "Wind Speed",
+            WeatherVariable::Humidity => "Humidity",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            WeatherVariable::Temperature | WeatherVariable::ApparentTemperature => "°C",
+            WeatherVariable::Precipitation => "mm",
+            WeatherVariable::WindSpeed => "km/h",
+            WeatherVariable::Humidity => "%",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            WeatherVariable::Temperature => WeatherVariable::ApparentTemperature,
+            WeatherVariable::ApparentTemperature => WeatherVariable::Precipitation,
+            WeatherVariable::Precipitation => WeatherVariable::WindSpeed,
+            WeatherVariable::WindSpeed => WeatherVariable::Humidity,
+            WeatherVariable::Humidity => WeatherVariable::Temperature,
+        }
+    }
+}
+
+impl HourlyData {
+    fn series(&self, variable: WeatherVariable) -> &[f64] {
+        match variable {
+            WeatherVariable::Temperature => &self.temperature_2m,
+            WeatherVariable::ApparentTemperature => &self.apparent_temperature,
+            WeatherVariable::Precipitation => &self.precipitation,
+            WeatherVariable::WindSpeed => &self.wind_speed_10m,
+            WeatherVariable::Humidity => &self.relative_humidity_2m,
+        }
+    }
+}
+
+// Get the body of the response, or the message explaining why the request failed
+fn request(url: &str) -> Result<String, String> {
+    match get(url) {
+        Ok(response) => {
+            match response.status() {
+                StatusCode::OK => response.text().map_err(|error| format!("Error: {}", error)),
+                StatusCode::BAD_REQUEST => Err("Error 400: Bad request".to_string()),
+                StatusCode::UNAUTHORIZED => Err("Error 401: Unauthorized".to_string()),
+                StatusCode::FORBIDDEN => Err("Error 403: Forbidden".to_string()),
+                StatusCode::NOT_FOUND => Err("Error 404: Not Found".to_string()),
+                //The API documentation says that number of requests per day is limited
+                StatusCode::TOO_MANY_REQUESTS => Err("Error 429: Too Many Requests".to_string()),
+                StatusCode::REQUEST_TIMEOUT => Err("Error 408: Request Timeout".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR => Err("Error 500: Internal Server Error".to_string()),
+                StatusCode::BAD_GATEWAY => Err("Error 502: Bad Gateway".to_string()),
+                StatusCode::SERVICE_UNAVAILABLE => Err("Error 503: Service Unavailable".to_string()),
+                StatusCode::GATEWAY_TIMEOUT => Err("Error 504: Gateway Timeout".to_string()),
+                //We will not mind about other HTTP errors
+                _ => Err("Error: Unknown HTTP Error".to_string()),
+            }
+        },
+        Err(error) => {
+            if error.is_connect() {Err("No internet connection".to_string())} else if error.is_timeout() {
+                Err("Request timed out".to_string())} else {
+                    Err(format!("Error: {}", error))
+            }
+        }
+    }
+}
+
+// Where the forecasts are fetched from. The URLs can be changed to use a mirror or a mock server.
+#[derive(Debug, Clone)]
+pub struct WeatherSource {
+    pub forecast_url: String,
+    pub geocoding_url: String,
+}
+
+impl WeatherSource {
+    // Find the coordinates of the location from its name. They are kept so that it is only looked up once.
+    fn locate(&self, location: &mut LocationConfig) -> Result<(f64, f64), String> {
+        if let Some(coordinates) = location.latitude.zip(location.longitude) {
+            return Ok(coordinates);
+        }
+        let url = Url::parse_with_params(&self.geocoding_url, &[("name", location.name.as_str()), ("count", "1")])
+            .map_err(|error| format!("Error: {}", error))?;
+        let response: GeocodingResponse = serde_json::from_str(&request(url.as_str())?)
+            .map_err(|_| "Error! Received an invalid json format".to_string())?;
+        let result = response.results.first().ok_or(format!("Unknown location {}", location.name))?;
+        location.latitude = Some(result.latitude);
+        location.longitude = Some(result.longitude);
+        Ok((result.latitude, result.longitude))
+    }
+
+    pub fn fetch(&self, location: &mut LocationConfig) -> Result<WeatherForecast, String> {
+        let (latitude, longitude) = self.locate(location)?;
+        // The times are given in the local time of the location
+        let url = Url::parse_with_params(&self.forecast_url, &[
+            ("latitude", latitude.to_string().as_str()),
+            ("longitude", longitude.to_string().as_str()),
+            ("hourly", HOURLY),
+            ("daily", DAILY),
+            ("timezone", "auto"),
+        ]).map_err(|error| format!("Error: {}", error))?;
+        serde_json::from_str::<WeatherForecast>(&request(url.as_str())?)
+            .map_err(|_| "Error! Received an invalid json format".to_string())
+    }
+}
+
+impl WeatherSource {
+    // Fetch the forecast and keep it in the cache when it succeeds
+    pub fn refresh(&self, location: &mut LocationConfig, cache: &mut HashMap<String, CachedForecast>, cache_path: &Path)
+        -> Result<CachedForecast, String> {
+        let cached = CachedForecast { updated_at: Utc::now().timestamp(), forecast: self.fetch(location)? };
+        cache.insert(location.name.clone(), cached.clone());
+        let _ = save_cache(cache_path, cache);
+        Ok(cached)
+    }
+}
+
+// Last successful forecast of each location, by name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedForecast {
+    updated_at: i64, // Unix timestamp
+    forecast: WeatherForecast,
+}
+
+// A missing or invalid cache is just empty
+pub fn load_cache(path: &Path) -> HashMap<String, CachedForecast> {
+    read_to_string(path).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(path: &Path, cache: &HashMap<String, CachedForecast>) -> io::Result<()> {
+    write(path, serde_json::to_string(cache).map_err(io::Error::other)?)
+}
+
+// Result of a refresh of the location at the given index
+pub struct WeatherUpdate {
+    pub index: usize,
+    pub result: Result<CachedForecast, String>,
+}
+
+// Refresh every location in the background, then wait for the interval before doing it again. The updates are sent
+// as any event the receiver understands, and the thread stops once the receiver is dropped.
+pub fn spawn_refresh<E: From<WeatherUpdate> + Send + 'static>(mut locations: Vec<LocationConfig>, source: WeatherSource,
+    interval: Duration, cache_path: PathBuf, sender: Sender<E>) {
+    thread::spawn(move || {
+        let mut cache = load_cache(&cache_path);
+        loop {
+            for (index, location) in locations.iter_mut().enumerate() {
+                let result = source.refresh(location, &mut cache, &cache_path);
+                if sender.send(WeatherUpdate { index, result }.into()).is_err() {
+                    return;
+                }
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+// Every variable at one hour, missing when the forecast doesn't have it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourlyPoint {
+    pub time: String,
+    pub temperature_2m: Option<f64>,
+    pub apparent_temperature: Option<f64>,
+    pub precipitation: Option<f64>,
+    pub wind_speed_10m: Option<f64>,
+    pub relative_humidity_2m: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct WeatherApp {
+    pub name: String,
+    forecast_weather: WeatherForecast,
+    updated_at: Option<DateTime<Utc>>,
+    refreshed: bool, // Whether the forecast was fetched since the start, rather than read from the cache
+    pub last_error: Option<String>,
+}
+
+impl WeatherApp {
+    // Start from the cached forecast, if any, until the first refresh
+    pub fn new(location: &LocationConfig, cache: &HashMap<String, CachedForecast>) -> Self {
+        let cached = cache.get(&location.name);
+        Self {
+            name: location.name.clone(),
+            forecast_weather: cached.map(|cached| cached.forecast.clone()).unwrap_or_default(),
+            updated_at: cached.and_then(|cached| DateTime::from_timestamp(cached.updated_at, 0)),
+            ..Self::default()
+        }
+    }
+
+    pub fn update(&mut self, result: Result<CachedForecast, String>) {
+        match result {
+            Ok(cached) => {
+                self.forecast_weather = cached.forecast;
+                self.updated_at = DateTime::from_timestamp(cached.updated_at, 0);
+                self.refreshed = true;
+                self.last_error = None;
+            },
+            Err(error) => self.last_error = Some(error),
+        }
+    }
+
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    // Every variable of the forecast from the current hour on, for a number of hours or until its end. The times are
+    // the local times of the location.
+    pub fn hourly(&self, hours: Option<usize>) -> Vec<HourlyPoint> {
+        let hourly = &self.forecast_weather.hourly;
+        let offset = self.forecast_weather.utc_offset_seconds;
+        let current_hour = Utc::now().timestamp() - 3600;
+        hourly.time.iter().enumerate()
+            .filter(|(_, time)| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
+                .is_ok_and(|time| time.and_utc().timestamp() - offset > current_hour))
+            .take(hours.unwrap_or(usize::MAX))
+            .map(|(index, time)| HourlyPoint {
+                time: time.clone(),
+                temperature_2m: hourly.temperature_2m.get(index).copied(),
+                apparent_temperature: hourly.apparent_temperature.get(index).copied(),
+                precipitation: hourly.precipitation.get(index).copied(),
+                wind_speed_10m: hourly.wind_speed_10m.get(index).copied(),
+                relative_humidity_2m: hourly.relative_humidity_2m.get(index).copied(),
+            })
+            .collect()
+    }
+
+    // Message shown above the chart when the forecast is out of date: it comes from the cache, the last refresh
+    // failed or no refresh succeeded for two intervals.
+    pub fn offline_banner(&self, interval: Duration) -> Option<String> {
+        let Some(updated_at) = self.updated_at else {
+            return self.last_error.clone();
+        };
+        let too_old = Utc::now().signed_duration_since(updated_at).to_std().is_ok_and(|age| age > interval * 2);
+        if self.refreshed && self.last_error.is_none() && !too_old {
+            return None;
+        }
+        let updated_at = updated_at.with_timezone(&Local).format("%Y/%m/%d %H:%M");
+        let reason = self.last_error.clone().unwrap_or_else(|| "waiting for a refresh".to_string());
+        Some(format!("Offline: showing the forecast of {updated_at} ({reason})"))
+    }
+
+    // function to prepare the format of the data to be incorporated into the ratatui chart object. The x values are
+    // hours since the Unix epoch, so that the forecasts of locations in different time zones line up on one chart.
+    pub fn get_dataset(&self, variable: WeatherVariable) -> Vec<(f64,f64)>{
+        let time=&self.forecast_weather.hourly.time ;
+        let values=self.forecast_weather.hourly.series(variable);
+        let offset=self.forecast_weather.utc_offset_seconds;
+        time.iter()
+        .zip(values.iter())
+        .filter_map(|(t,&value)| {
+            // The times which can't be parsed are skipped rather than crashing the dashboard
+            let parsed_datetime= NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M").ok()?;
+            let x=(parsed_datetime.and_utc().timestamp()-offset) as f64 /3600.0;
+            Some((x,value))
+        }).collect()
+    }
+
+    // Minimum and maximum temperatures of each day, like "Mon 20: 4.5°/12.3°"
+    pub fn daily_summary(&self) -> Vec<String> {
+        let daily = &self.forecast_weather.daily;
+        daily.time.iter()
+            .zip(daily.temperature_2m_min.iter().zip(daily.temperature_2m_max.iter()))
+            .map(|(day, (min, max))| {
+                let day = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                    .map(|date| date.format("%a %d").to_string())
+                    .unwrap_or_else(|_| day.clone());
+                format!("{day}: {min:.0}°/{max:.0}°")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::{BufRead, BufReader, Write}, net::TcpListener, sync::mpsc::channel};
+
+    const FORECAST: &str = r#"{"latitude": 48.86, "longitude": 2.35, "timezone": "Europe/Paris", "utc_offset_seconds": 3600,
+        "hourly": {"time": ["2025-01-20T00:00", "2025-01-20T01:00", "2025-01-20T02:00"],
+            "temperature_2m": [4.0, 3.5, 3.0], "precipitation": [0.0, 0.2, 0.0],
+            "wind_speed_10m": [10.0, 12.0, 9.0], "relative_humidity_2m": [80.0, 85.0, 90.0]},
+        "daily": {"time": ["2025-01-20"], "temperature_2m_max": [12.3], "temperature_2m_min": [4.5]}}"#;
+
+    // Local server answering each request with the next of the given responses, and reporting the requested paths
+    fn mock_server(responses: Vec<(u16, &'static str)>, paths: Sender<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers, the requests have no body
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                paths.send(path).unwrap();
+                write!(stream, "HTTP/1.1 {status} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+                    .unwrap();
+            }
+        });
+        url
+    }
+
+    fn location(name: &str, coordinates: Option<(f64, f64)>) -> LocationConfig {
+        LocationConfig {
+            name: name.to_string(),
+            latitude: coordinates.map(|(latitude, _)| latitude),
+            longitude: coordinates.map(|(_, longitude)| longitude),
+        }
+    }
+
+    fn source(url: &str) -> WeatherSource {
+        WeatherSource { forecast_url: format!("{url}/v1/forecast"), geocoding_url: format!("{url}/v1/search") }
+    }
+
+    #[test]
+    fn fetches_the_forecast_of_coordinates() {
+        let (sender, paths) = channel();
+        let url = mock_server(vec![(200, FORECAST)], sender);
+        let forecast = source(&url).fetch(&mut location("Paris", Some((48.86, 2.35)))).unwrap();
+        let path = paths.recv().unwrap();
+        assert!(path.starts_with("/v1/forecast?latitude=48.86&longitude=2.35&hourly="), "{path}");
+        assert!(path.ends_with("&timezone=auto"), "{path}");
+
+        let mut weather_app = WeatherApp::new(&location("Paris", None), &HashMap::new());
+        weather_app.update(Ok(CachedForecast { updated_at: Utc::now().timestamp(), forecast }));
+        // Midnight in Paris is 23:00 UTC the day before
+        let start = DateTime::parse_from_rfc3339("2025-01-19T23:00:00Z").unwrap().timestamp() as f64 / 3600.0;
+        assert_eq!(weather_app.get_dataset(WeatherVariable::Precipitation),
+            vec![(start, 0.0), (start + 1.0, 0.2), (start + 2.0, 0.0)]);
+        assert_eq!(weather_app.daily_summary(), vec!["Mon 20: 4°/12°"]);
+        assert_eq!(weather_app.offline_banner(Duration::from_secs(1800)), None);
+    }
+
+    #[test]
+    fn looks_up_the_coordinates_of_a_name_once() {
+        let (sender, paths) = channel();
+        let url = mock_server(vec![
+            (200, r#"{"results": [{"latitude": 48.86, "longitude": 2.35}]}"#),
+            (200, FORECAST),
+            (200, FORECAST),
+        ], sender);
+        let source = source(&url);
+        let mut paris = location("Paris", None);
+        source.fetch(&mut paris).unwrap();
+        assert_eq!((paris.latitude, paris.longitude), (Some(48.86), Some(2.35)));
+        source.fetch(&mut paris).unwrap();
+        let paths: Vec<String> = paths.try_iter().collect();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], "/v1/search?name=Paris&count=1");
+        assert!(paths[1..].iter().all(|path| path.starts_with("/v1/forecast?")));
+    }
+
+    #[test]
+    fn reports_unknown_locations_and_http_errors() {
+        let (sender, _paths) = channel();
+        let url = mock_server(vec![(200, "{}"), (500, ""), (200, "not json")], sender);
+        let source = source(&url);
+        assert_eq!(source.fetch(&mut location("Atlantis", None)).unwrap_err(), "Unknown location Atlantis");
+        let mut paris = location("Paris", Some((48.86, 2.35)));
+        assert_eq!(source.fetch(&mut paris).unwrap_err(), "Error 500: Internal Server Error");
+        assert_eq!(source.fetch(&mut paris).unwrap_err(), "Error! Received an invalid json format");
+    }
+
+    #[test]
+    fn shows_the_cached_forecast_as_offline() {
+        let forecast: WeatherForecast = serde_json::from_str(FORECAST).unwrap();
+        let cache = HashMap::from([("Paris".to_string(), CachedForecast { updated_at: 0, forecast })]);
+        let mut weather_app = WeatherApp::new(&location("Paris", None), &cache);
+        assert!(!weather_app.get_dataset(WeatherVariable::Temperature).is_empty());
+        let banner = weather_app.offline_banner(Duration::from_secs(1800)).unwrap();
+        assert!(banner.contains("waiting for a refresh"), "{banner}");
+
+        // A failed refresh keeps the cached forecast
+        weather_app.update(Err("No internet connection".to_string()));
+        assert!(!weather_app.get_dataset(WeatherVariable::Temperature).is_empty());
+        let banner = weather_app.offline_banner(Duration::from_secs(1800)).unwrap();
+        assert!(banner.starts_with("Offline: showing the forecast of "), "{banner}");
+        assert!(banner.ends_with("(No internet connection)"), "{banner}");
+    }
+
+    #[test]
+    fn empty_forecast_has_no_data() {
+        let mut weather_app = WeatherApp::new(&location("Paris", None), &HashMap::new());
+        assert!(weather_app.get_dataset(WeatherVariable::Temperature).is_empty());
+        assert_eq!(weather_app.offline_banner(Duration::from_secs(1800)), None);
+        weather_app.update(Err("No internet connection".to_string()));
+        assert_eq!(weather_app.offline_banner(Duration::from_secs(1800)), Some("No internet connection".to_string()));
+    }
+}
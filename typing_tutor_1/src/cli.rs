@@ -0,0 +1,148 @@
+// Command line interface of the typing tutor. Every option is optional: without a mode, the tutor asks for one
+// interactively as it always did.
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{io, path::{Path, PathBuf}, time::Duration};
+
+use crate::{get_typing_samples, layout::DEFAULT_LAYOUT, modes::{TestMode, TIMED_DURATIONS}, race::DEFAULT_PORT, sources::TextSource};
+
+#[derive(Debug, Parser)]
+#[command(name = "typing_tutor", about = "Practice your typing in the terminal")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Sample file, or directory containing the samples (source files in code mode)
+    #[arg(short, long, global = true)]
+    pub samples: Option<PathBuf>,
+    /// Only take the files of the directory whose name starts with this prefix.
+    /// Defaults to "typing" when no directory is given.
+    #[arg(short, long, global = true)]
+    pub prefix: Option<String>,
+    /// List the available samples and exit
+    #[arg(short, long)]
+    pub list: bool,
+    /// Take the samples in a random order
+    #[arg(long)]
+    pub shuffle: bool,
+    /// Kind of test to take. The tutor asks for it if it is not given.
+    #[arg(short, long, value_enum)]
+    pub mode: Option<Mode>,
+    /// Read the text to type from the standard input, one exercise per paragraph (implies the sample mode)
+    #[arg(long, group = "source", conflicts_with = "mode")]
+    pub stdin: bool,
+    /// Markdown (.md) or plain text file split into one exercise per paragraph (implies the sample mode)
+    #[arg(long, group = "source", conflicts_with = "mode")]
+    pub document: Option<PathBuf>,
+    /// File with one quote per line, followed by " — " and its author (implies the sample mode)
+    #[arg(long, group = "source", conflicts_with = "mode")]
+    pub quotes: Option<PathBuf>,
+    /// Duration of the timed test in seconds (15, 30, 60 or 120)
+    #[arg(long, default_value_t = 60, value_parser = parse_duration)]
+    pub duration: u64,
+    /// Number of words of the word count test
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u64).range(1..))]
+    pub words: u64,
+    /// Skip the leading indentation of each line in code mode
+    #[arg(long)]
+    pub auto_indent: bool,
+    /// Typing speed you are aiming at, in words per minute
+    #[arg(short, long)]
+    pub target_wpm: Option<f32>,
+    /// Don't use colours in the output
+    #[arg(long, global = true)]
+    pub no_color: bool,
+    /// Keyboard layout: qwerty, azerty, qwertz, dvorak, colemak or the path of a layout file
+    #[arg(long, global = true, default_value = DEFAULT_LAYOUT)]
+    pub layout: String,
+    /// Hide the on-screen keyboard and the heatmap of the mistakes
+    #[arg(long, global = true)]
+    pub no_keyboard: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Replay a recorded session in the terminal, then show its analysis report
+    Replay {
+        /// Session file saved in the sessions folder
+        session: String,
+        /// Replay speed, 2 being twice as fast as the real session
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+    /// Show the analysis report of a recorded session
+    Report {
+        /// Session file saved in the sessions folder
+        session: String,
+    },
+    /// Race other players over the local network
+    Race {
+        #[command(subcommand)]
+        action: RaceAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RaceAction {
+    /// Host the lobby. The race text is a random exercise from the samples or text source.
+    Host {
+        /// Port to listen on
+        #[arg(long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+        /// Name shown to the other players
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Join a lobby hosted at the given address
+    Join {
+        /// Address of the host
+        #[arg(default_value_t = format!("127.0.0.1:{DEFAULT_PORT}"))]
+        address: String,
+        /// Name shown to the other players
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Mode {
+    Sample,
+    Timed,
+    Words,
+    Code,
+}
+
+impl Cli {
+    // The test mode asked on the command line, if any. The text sources can only be used by the sample mode.
+    pub fn test_mode(&self) -> Option<TestMode> {
+        if self.stdin || self.document.is_some() || self.quotes.is_some() {
+            return Some(TestMode::Sample);
+        }
+        self.mode.map(|mode| match mode {
+            Mode::Sample => TestMode::Sample,
+            Mode::Timed => TestMode::Timed(Duration::from_secs(self.duration)),
+            Mode::Words => TestMode::Words(self.words as usize),
+            Mode::Code => TestMode::Code { auto_indent: self.auto_indent },
+        })
+    }
+
+    // Where the exercises of the sample mode come from, the sample files being the default
+    pub fn text_source(&self, samples_path: &Path, prefix: &str) -> io::Result<TextSource> {
+        Ok(if self.stdin {
+            TextSource::Stdin
+        } else if let Some(path) = &self.document {
+            TextSource::Document(path.clone())
+        } else if let Some(path) = &self.quotes {
+            TextSource::Quotes(path.clone())
+        } else {
+            TextSource::Files(get_typing_samples(samples_path, prefix)?)
+        })
+    }
+}
+
+fn parse_duration(input: &str) -> Result<u64, String> {
+    let seconds: u64 = input.parse().map_err(|_| format!("{input} is not a number of seconds"))?;
+    if TIMED_DURATIONS.contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(format!("the duration must be one of {TIMED_DURATIONS:?}"))
+    }
+}
@@ -0,0 +1,91 @@
+// This module gathers what the code practice mode needs: loading a source file, finding out how it is
+// indented and printing it with a simple syntax highlighting so that it looks like it does in an editor.
+use ansi_term::Colour::{Cyan, Fixed, Green, Purple, Yellow};
+use std::{fs::read_to_string, io};
+
+const RUST_KEYWORDS: [&str; 39] = ["as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+    "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "yield"];
+
+// Windows line endings are replaced so that Enter only has to insert '\n', and the trailing blank lines are
+// removed because nobody wants to type them.
+pub fn load_code(file_name: &str) -> io::Result<String> {
+    let content = read_to_string(file_name)?;
+    Ok(content.replace("\r\n", "\n").trim_end().to_string())
+}
+
+// The indentation inserted by the Tab key: a tab if the file is indented with tabs, otherwise the smallest
+// number of leading spaces found in the file (4 by default).
+pub fn indent_unit(code: &str) -> String {
+    if code.lines().any(|line| line.starts_with('\t')) {
+        return "\t".to_string();
+    }
+    let spaces = code.lines()
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .filter(|count| *count > 0)
+        .min()
+        .unwrap_or(4);
+    " ".repeat(spaces)
+}
+
+// The whitespace found in the code from the given character position, used to skip the indentation
+pub fn leading_indent(code: &str, position: usize) -> String {
+    code.chars().skip(position).take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+// Colour the keywords, strings, comments, numbers and macros or types of the code.
+pub fn highlight_code(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            let comment: String = chars[start..i].iter().collect();
+            output.push_str(&Fixed(244).paint(comment).to_string());
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                // Skip the escaped characters such as \" so that they don't end the string
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            let string: String = chars[start..i].iter().collect();
+            output.push_str(&Green.paint(string).to_string());
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            output.push_str(&Yellow.paint(number).to_string());
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if RUST_KEYWORDS.contains(&word.as_str()) {
+                output.push_str(&Purple.bold().paint(word).to_string());
+            } else if chars.get(i) == Some(&'!') {
+                // Macros keep their exclamation mark
+                i += 1;
+                output.push_str(&Cyan.paint(format!("{word}!")).to_string());
+            } else if c.is_uppercase() {
+                output.push_str(&Cyan.paint(word).to_string());
+            } else {
+                output.push_str(&word);
+            }
+        } else {
+            output.push(c);
+            i += 1;
+        }
+    }
+    output
+}
@@ -0,0 +1,225 @@
+// Keyboard layouts are described in the data files of the "layouts" folder, which are built into the program so that
+// it runs from any directory. They tell the tutor which accented letters can be typed directly, where the next key to
+// press is on the on-screen keyboard and which physical keys got the most mistakes once the test is over.
+use ansi_term::{Colour::{Black, Fixed, Red, White, Yellow}, Style};
+use std::{collections::HashMap, fs::read_to_string, io, path::Path};
+use unicode_normalization::UnicodeNormalization;
+
+pub const DEFAULT_LAYOUT: &str = "azerty";
+const BUILT_IN_LAYOUTS: [(&str, &str); 5] = [
+    ("azerty", include_str!("../layouts/azerty.txt")),
+    ("colemak", include_str!("../layouts/colemak.txt")),
+    ("dvorak", include_str!("../layouts/dvorak.txt")),
+    ("qwerty", include_str!("../layouts/qwerty.txt")),
+    ("qwertz", include_str!("../layouts/qwertz.txt")),
+];
+
+// Position of a physical key on the keyboard. The space bar is on the row below the last row of keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub name: String,
+    rows: Vec<Vec<Vec<char>>>, // Every key lists its characters: normal, shifted then AltGr
+    extra: Vec<char>,          // Characters typed with dead keys, which have no key of their own
+}
+
+impl Layout {
+    // The layout can be given by its name ("qwerty") or by the path of a layout file. Only the files are read from
+    // the disk.
+    pub fn load(name: &str) -> io::Result<Self> {
+        if Path::new(name).is_file() {
+            return Self::parse(name, &read_to_string(name)?);
+        }
+        match BUILT_IN_LAYOUTS.iter().find(|(built_in, _)| name.eq_ignore_ascii_case(built_in)) {
+            Some((_, content)) => Self::parse(name, content),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("no layout file and no built-in layout named {name}"))),
+        }
+    }
+
+    fn parse(name: &str, content: &str) -> io::Result<Self> {
+        let mut layout = Layout { name: name.to_string(), rows: Vec::new(), extra: Vec::new() };
+        for line in content.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("name:") {
+                layout.name = name.trim().to_string();
+            } else if let Some(extra) = line.strip_prefix("extra:") {
+                layout.extra = extra.split_whitespace().filter_map(|c| c.chars().next()).collect();
+            } else {
+                layout.rows.push(line.split_whitespace().map(|key| key.chars().collect()).collect());
+            }
+        }
+        if layout.rows.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{name} has no rows of keys")));
+        }
+        Ok(layout)
+    }
+
+    // The physical key typing this character. The accented letters without a key of their own are found
+    // through their base letter.
+    pub fn find(&self, c: char) -> Option<KeyPosition> {
+        if c == ' ' {
+            return Some(KeyPosition { row: self.rows.len(), column: 0 });
+        }
+        let position = self.rows.iter().enumerate().find_map(|(row, keys)| {
+            keys.iter().position(|key| key.contains(&c)).map(|column| KeyPosition { row, column })
+        });
+        position.or_else(|| {
+            let base = c.nfd().next().filter(|base| *base != c)?;
+            self.find(base)
+        })
+    }
+
+    // The "e" letters other than e and E which can be typed directly with this layout, the ones of the dead keys
+    // included. With AZERTY, this is é è ê Ê as before the layouts, plus ë Ë of the ¨ dead key which are now
+    // scored like a plain e as well.
+    pub fn e_variants(&self) -> Vec<char> {
+        self.rows.iter().flatten().flatten().chain(self.extra.iter())
+            .filter(|c| !matches!(c, 'e' | 'E') && matches!(c.nfd().next(), Some('e' | 'E')))
+            .copied()
+            .collect()
+    }
+
+    // Draw the keyboard with the key of the next character highlighted
+    pub fn draw(&self, next: Option<char>, color: bool) {
+        let next_key = next.and_then(|c| self.find(c));
+        self.draw_keys(color, |position| {
+            (next_key == Some(position)).then(|| Style::new().reverse())
+        });
+        if let Some(c) = next {
+            let shifted = self.rows.iter().flatten().any(|key| key.get(1) == Some(&c));
+            if shifted {
+                println!("Next key: {c} (Shift)");
+            }
+        }
+    }
+
+    // Draw the keyboard coloured by the number of mistakes made on each key, then the most missed keys
+    pub fn draw_heatmap(&self, mistakes: &HashMap<char, usize>, color: bool) {
+        let mut per_key: HashMap<KeyPosition, usize> = HashMap::new();
+        for (c, count) in mistakes {
+            if let Some(position) = self.find(*c) {
+                *per_key.entry(position).or_insert(0) += count;
+            }
+        }
+        let max = per_key.values().copied().max().unwrap_or(0);
+        if max == 0 {
+            println!("No mistakes, well done!");
+            return;
+        }
+        println!("Mistakes per key ({} layout):", self.name);
+        self.draw_keys(color, |position| {
+            let count = per_key.get(&position).copied().unwrap_or(0);
+            match count * 3 {
+                0 => None,
+                scaled if scaled <= max => Some(Black.on(Yellow)),
+                scaled if scaled <= 2 * max => Some(Black.on(Fixed(208))),
+                _ => Some(White.on(Red)),
+            }
+        });
+        let mut most_missed: Vec<(KeyPosition, usize)> = per_key.into_iter().collect();
+        most_missed.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        let most_missed: Vec<String> = most_missed.iter().take(5)
+            .map(|(position, count)| format!("{} ({count})", self.label(*position)))
+            .collect();
+        println!("Most missed keys: {}", most_missed.join(", "));
+    }
+
+    fn label(&self, position: KeyPosition) -> String {
+        match self.rows.get(position.row) {
+            Some(keys) => keys[position.column].first().map(|c| c.to_string()).unwrap_or_default(),
+            None => "space".to_string(),
+        }
+    }
+
+    // Each row is shifted a little to the right like on a real keyboard. Without colours, the highlighted keys
+    // are written between angle brackets.
+    fn draw_keys(&self, color: bool, style: impl Fn(KeyPosition) -> Option<Style>) {
+        let rows = self.rows.len();
+        for row in 0..=rows {
+            let columns = self.rows.get(row).map_or(1, |keys| keys.len());
+            let mut line = " ".repeat(row * 2);
+            for column in 0..columns {
+                let position = KeyPosition { row, column };
+                let label = if row == rows {"      space      ".to_string()} else {self.label(position)};
+                let key = match (style(position), color) {
+                    (Some(style), true) => style.paint(format!("[{label}]")).to_string(),
+                    (Some(_), false) => format!("<{label}>"),
+                    (None, _) => format!("[{label}]"),
+                };
+                line.push_str(&key);
+            }
+            println!("{line}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::{remove_file, write}};
+
+    fn load_file(name: &str, content: &str) -> io::Result<Layout> {
+        let path = temp_dir().join(format!("layout_{name}_{}.txt", std::process::id()));
+        write(&path, content).unwrap();
+        let layout = Layout::load(path.to_str().unwrap());
+        remove_file(&path).unwrap();
+        layout
+    }
+
+    #[test]
+    fn layouts_load_by_name_or_path() {
+        let layout = Layout::load("QWERTY").unwrap();
+        assert_eq!(layout.name, "QWERTY");
+        assert_eq!(layout.find('q'), Some(KeyPosition { row: 1, column: 0 }));
+        let layout = load_file("custom", "# comment\nname: Mine\naA bB\n\ncC\nextra: ä\n").unwrap();
+        assert_eq!(layout.name, "Mine");
+        assert_eq!(layout.find('B'), Some(KeyPosition { row: 0, column: 1 }));
+        assert_eq!(layout.find('c'), Some(KeyPosition { row: 1, column: 0 }));
+        assert_eq!(layout.extra, vec!['ä']);
+        // The space bar is below the last row
+        assert_eq!(layout.find(' '), Some(KeyPosition { row: 2, column: 0 }));
+    }
+
+    #[test]
+    fn built_in_layouts_need_no_file() {
+        for (name, _) in BUILT_IN_LAYOUTS {
+            let layout = Layout::load(&name.to_uppercase()).unwrap();
+            assert!(layout.find('a').is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn layout_without_keys_is_refused() {
+        let error = load_file("empty", "name: Empty\nextra: é\n").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Layout::load("no such layout").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn dead_key_letters_are_found_on_their_base_key() {
+        let layout = Layout::load("azerty").unwrap();
+        let e_key = layout.find('e');
+        assert!(e_key.is_some());
+        assert_eq!(layout.find('ê'), e_key);
+        assert_eq!(layout.find('Ê'), e_key);
+        // é has its own key on the number row
+        assert_eq!(layout.find('é'), Some(KeyPosition { row: 0, column: 2 }));
+        assert_eq!(layout.find('Ü'), layout.find('u'));
+        assert_eq!(layout.find('ñ'), layout.find('n'));
+        assert_eq!(layout.find('☃'), None);
+    }
+
+    #[test]
+    fn e_variants_of_the_layouts() {
+        assert_eq!(Layout::load("azerty").unwrap().e_variants(), vec!['é', 'è', 'ê', 'Ê', 'ë', 'Ë']);
+        assert!(Layout::load("qwerty").unwrap().e_variants().is_empty());
+        assert_eq!(Layout::load("qwertz").unwrap().e_variants(), vec!['ê', 'Ê', 'é', 'É', 'è', 'È']);
+    }
+}
@@ -1,155 +1,192 @@
 //This main file tries to answer the first question about giving the time which the test takes and 
 // giving the typing speed in wpm
-use crossterm::{event::{ read, Event, KeyCode, KeyEventKind}, terminal::{Clear, ClearType}, execute};
-use std::{fs::{read_to_string, read_dir}, io::stdout, io::stdin, time::Instant};
+mod cli;
+mod code;
+mod layout;
+mod modes;
+mod race;
+mod session;
+mod sources;
+mod typing_session;
+
+use crossterm::{event::{poll, read, Event}, terminal::{Clear, ClearType}, execute};
+use std::{fs::read_dir, io::stdout, io::stdin, path::Path, time::{Duration, Instant}};
 use ansi_term::Colour::Red;
-use unicode_normalization::{char::compose, UnicodeNormalization};
 use std::io;
-use regex::Regex;
-struct App {
-    file_content: String,
-    user_input: String,
-    hidden_input: String,// This is a flag to save the code to compose accented letters with unicode
+use clap::Parser;
+use cli::{Cli, Command, RaceAction};
+use code::{highlight_code, load_code};
+use layout::Layout;
+use modes::{TestMode, WordStream, TIMED_DURATIONS, WORDS_FILE};
+use session::Session;
+use sources::{Exercise, TextSource};
+use typing_session::{Status, TypingRules, TypingSession};
+
+// Options changing the way the tests are displayed and scored, set from the command line
+#[derive(Debug, Clone)]
+struct Settings {
+    color: bool,
+    target_wpm: Option<f32>,
+    layout: Layout,
+    keyboard: bool,// Show the on-screen keyboard and the heatmap of the mistakes
 }
 
-impl App {
-    fn new(file_name: &str) -> Result<Self, std::io::Error> {
-        let file_content=read_to_string(file_name)?;
-        Ok(Self {
-            file_content,
-            user_input: String::new(),
-            hidden_input: String::new(),
-        })
+// Print the text to type then what the user typed so far, with the mistakes in red
+fn draw_text(text: &str, typed: &str, highlight: bool, color: bool) {
+    if highlight && color {
+        println!("{}",highlight_code(text));
+    } else {
+        println!("{}",text);
     }
+    for (letter1,letter2) in typed.chars().zip(text.chars()){
+        if letter1==letter2{
+            print!("{letter2}");
+        } else if !color {
+            print!("*");
+        } else {
+            // show incorrect entries in red instead of just an asterisk
+            // convert letter1 to string in order to implement the Into<Cow<'_, _>>` trait
+            print!("{}",Red.paint(letter1.to_string()));
+        }
+    }
+    // The underscore shows the user where the cursor is.
+    println!("_");
 }
 
-fn implement_accented_word(char: char,hex: &String) -> Option<char> {
-    let content = read_to_string("diacritical_marks.txt").ok()?;
-    let accents_hex_codes: Vec<&str> = content.lines().collect();
-    if accents_hex_codes.contains(&hex.as_str()){
-        // Parse the string into a u32
-        let unicode_value = u32::from_str_radix(&hex, 16).ok()?;
-        // Convert the u32 into a char
-        let char_output=char::from_u32(unicode_value).unwrap_or('\u{034F}');
-        compose(char,char_output)
-    } else {
-        Some(char)
+// Print the results of the test and save its keystrokes so that it can be replayed later
+fn finish_test(typing: TypingSession, settings: &Settings) {
+    if let Some(result)=typing.result() {
+        result.print(settings.target_wpm);
+    }
+    if settings.keyboard {
+        settings.layout.draw_heatmap(typing.mistakes(), settings.color);
     }
+    match typing.into_recording().save() {
+        Ok(path) => println!("Session saved to {}", path.display()),
+        Err(error) => println!("Could not save the session: {error}"),
+    }
+}
+
+fn typing_tutor_per_sample(exercise: &Exercise, settings: &Settings) -> Result<(), std::io::Error> {
+    let typing=TypingSession::new(&exercise.text, TestMode::Sample, TypingRules::load(&settings.layout));
+    run_typing_test(typing, settings)?;
+    if let Some(attribution) = &exercise.attribution {
+        println!("That was a quote by {attribution}");
+    }
+    Ok(())
 }
 
-fn typing_tutor_per_sample(file_name:&str) -> Result<(), std::io::Error> {
-    let mut app= App::new(file_name)?;
+// Timed and word count tests draw random words from the word list instead of reading a sample file
+fn typing_tutor_random_words(mode: TestMode, settings: &Settings) -> Result<(), std::io::Error> {
+    let limit=match mode {
+        TestMode::Words(count) => Some(count),
+        _ => None,
+    };
+    let typing=TypingSession::from_stream(WordStream::new(WORDS_FILE, limit)?, mode, TypingRules::load(&settings.layout));
+    run_typing_test(typing, settings)
+}
+
+// In the code practice mode, the file is typed line by line and the test ends once it has been entirely
+// typed or when Ctrl+D is pressed.
+fn typing_tutor_code(file_name: &str, mode: TestMode, settings: &Settings) -> Result<(), std::io::Error> {
+    let typing=TypingSession::new(&load_code(file_name)?, mode, TypingRules::load(&settings.layout));
+    run_typing_test(typing, settings)
+}
+
+// The terminal side of the test: draw the text, then feed the key events to the typing session until it is over
+fn run_typing_test(mut typing: TypingSession, settings: &Settings) -> Result<(), std::io::Error> {
     let before_test=Instant::now();
-    // Variable to control the accented letters through the down key instead of alt key
-    let mut down_pressed = false;
-    // variable to control the cases of accented "e" letters obtained through unicode composition
-    let re_contains_e= Regex::new(r"([eE])\p{M}*").unwrap();
-    let e_vector_without_unicode=vec!['é','è','ê','Ê']; //This vec includes the instances of "e" which we can type directly from the keyboard
+    let code_mode=matches!(typing.mode(), TestMode::Code {..});
     loop {
         
         // Recall the unwrap method should not be used in production code because you could never predict the
         // outcome of the read() function.
-        println!("{}",app.file_content);
-        // The underscore shows the user where the cursor is.
         // Note the Backspace and Esc events will not work as expected in terminals other than Powershell
         // windows cmd. You can find more details in the docs page of KeyEventKind
-        for (letter1,letter2) in app.user_input.chars().zip(app.file_content.chars()){
-            if letter1==letter2{
-                print!("{letter2}");
-            } else {
-                // show incorrect entries in red instead of just an asterisk
-                // convert letter1 to string in order to implement the Into<Cow<'_, _>>` trait
-                print!("{}",Red.paint(letter1.to_string()));
+        draw_text(typing.text(), typing.typed(), code_mode, settings.color);
+        if settings.keyboard {
+            // Highlight the key of the next character to type
+            settings.layout.draw(typing.next_char(), settings.color);
+        }
+        if typing.is_composing() {
+            println!("Accent code: {}_ (press Enter to compose it with the previous letter)", typing.hidden_input());
+        }
+        if code_mode {
+            println!("Press Ctrl+D to end the test before the end of the file.");
+        }
+        if typing.tick(before_test.elapsed())==Status::Finished {
+            finish_test(typing, settings);
+            return Ok(());
+        }
+        if let Some(time_left) = typing.time_left(before_test.elapsed()) {
+            println!("Time left: {}s", time_left.as_secs());
+            // Wake up every second to refresh the countdown and stop the test as soon as the time is up
+            if !poll(time_left.min(Duration::from_secs(1)))? {
+                execute!(stdout(),Clear(ClearType::All))?;
+                continue;
             }
         }
-        println!("_");
         if let Event::Key(key_event)= read()? {
-            if key_event.kind==KeyEventKind::Press {
-                match key_event.code {
-                    KeyCode::Backspace => {
-                        if down_pressed { 
-                            // Remove from hidden_input if DOWN is pressed
-                            app.hidden_input.pop();} else {
-                        app.user_input.pop();}
-                    },
-                    KeyCode::Esc => break,
-                    KeyCode::Char(c) => {
-                        // keep user_input from getting longer than the file_content string
-                            if app.user_input.len() < app.file_content.len(){
-                                if re_contains_e.is_match(&c.to_string()) || e_vector_without_unicode
-                                .contains(&c){
-                                    app.user_input.push('|');//I don't recommend to use a space instead, cause it will 
-                                    //introduce a bias to the way the score and speed are computed
-                                } else {
-                                app.user_input.push(c);
-                            }
-                        }
-                    },
-                    KeyCode::Enter => {
-                        let normalized_text:String=app.file_content.nfd().collect(); 
-                        // Transform the file content according to the regex pattern in order to better compute the score
-                        // of total_chars later on.
-                        let transformed_file_content = re_contains_e
-                            .replace_all(&normalized_text, |caps: &regex::Captures| {caps[1].to_lowercase()});
-                        let total_chars=transformed_file_content.chars().filter(|b| *b!='e').count();
-                        let total_right=app.user_input.chars().zip(app.file_content.chars())
-                            .filter(|(a,b)| a==b && *a!='|').count();//letter e replaced by "|" should not be 
-                        // taken into account. 
-                        println!("you got {total_right} out of {total_chars}!");
-                        let after_test=before_test.elapsed();
-                        println!("The test took {:?}", after_test);
-                        let typing_length=app.user_input.split_whitespace().count() as f32;
-                        let typing_speed=typing_length / after_test.as_secs_f32()*60.0;
-                        println!("Typing speed: {} wpm", typing_speed);
-                        
-                        return Ok(());
-                    },
-                    KeyCode::Down => {
-                        loop{
-                        if let Event::Key(hidden_key_event)= read()? {
-                            if hidden_key_event.kind==KeyEventKind::Press {
-                                match hidden_key_event.code {
-                                    KeyCode::Char(c) => {
-                                        app.hidden_input.push(c);
-                                    },
-                                    KeyCode::Enter => break,
-                                    _ => {}
-                                }
-                            }
-                            }
-                    }
-                    },
-                    _ => {}
-                }
+            match typing.handle_key(key_event, before_test.elapsed()) {
+                Status::Finished => {
+                    finish_test(typing, settings);
+                    return Ok(());
+                },
+                Status::Aborted => break,
+                Status::Typing => {},
             }
-            // Handling the Down key release event
-            if key_event.kind == KeyEventKind::Release {
-                if down_pressed {
-                    // On DOWN key release, compose the characters
-                    if let Some(last_char) = app.user_input.pop() {
-                        // Compose the character typed before pressing DOWN with the hidden_input
-                        let accented_word:Option<char>  =implement_accented_word(last_char, &app.hidden_input);
-                        if let Some(accented_char) = accented_word {
-                            app.user_input.push(accented_char);
-                        } else {app.user_input.push(last_char);}
-                        app.hidden_input.clear(); // Reset hidden_input after composition
-                    }
-                    down_pressed = false; // Reset default state
+        }
+        execute!(stdout(),Clear(ClearType::All))?;
+    }
+    Ok(())
+}
+
+// Ask the user which kind of test to take. Anything else than 2, 3 or 4 falls back to the sample files.
+fn choose_mode() -> TestMode {
+    let mut input_string = String::new();
+    println!("Choose a test mode: [1] Sample files [2] Timed test [3] Word count test [4] Code practice");
+    stdin().read_line(&mut input_string).unwrap();
+    match input_string.trim() {
+        "2" => {
+            let durations: Vec<String>=TIMED_DURATIONS.iter().map(|d| d.to_string()).collect();
+            loop {
+                println!("How many seconds should the test last? [{}]", durations.join("/"));
+                input_string.clear();
+                stdin().read_line(&mut input_string).unwrap();
+                match input_string.trim().parse::<u64>() {
+                    Ok(seconds) if TIMED_DURATIONS.contains(&seconds) => return TestMode::Timed(Duration::from_secs(seconds)),
+                    _ => println!("Invalid input. Please enter one of the durations above."),
                 }
             }
-            // Track if Down key is being pressed
-            if key_event.code == KeyCode::Down {
-                down_pressed = true;
+        },
+        "3" => {
+            loop {
+                println!("How many words do you want to type?");
+                input_string.clear();
+                stdin().read_line(&mut input_string).unwrap();
+                match input_string.trim().parse::<usize>() {
+                    Ok(count) if count > 0 => return TestMode::Words(count),
+                    _ => println!("Invalid input. Please enter a positive number."),
+                }
             }
+        },
+        "4" => {
+            println!("Do you want the indentation to be skipped automatically? [Y/N]");
+            input_string.clear();
+            stdin().read_line(&mut input_string).unwrap();
+            TestMode::Code {auto_indent: matches!(input_string.trim(), "Y" | "y")}
+        },
+        _ => TestMode::Sample,
     }
-            execute!(stdout(),Clear(ClearType::All))?;
-        }
-    Ok(())
 }
 
-//function to retrieve the file names of the parent folder which start with the "typing" pattern
-fn get_typing_samples() -> io::Result<Vec<String>> {
-    let files=read_dir(".")?
+// Function to retrieve the sample files. A file is taken as is, while a directory gives the files whose name starts
+// with the prefix. In the project folder, we admit that all test files start with the word "typing".
+fn get_typing_samples(path: &Path, prefix: &str) -> io::Result<Vec<String>> {
+    if path.is_file() {
+        return Ok(vec![path.display().to_string()]);
+    }
+    let mut files: Vec<String>=read_dir(path)?
     .filter_map(|entry| entry.ok())
     .filter(|entry| {//functional programming to control the existence of the file
         match entry.file_type() {
@@ -157,56 +194,224 @@ fn get_typing_samples() -> io::Result<Vec<String>> {
             Err(_) => false,
         }
     })
-    .filter_map(|entry| entry.file_name().to_str().map(String::from))
-    .filter(|name| name.starts_with("typing"))//We admit here that all test files start with the word "typing".
+    .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.starts_with(prefix)))
+    .filter_map(|entry| entry.path().to_str().map(String::from))
     .collect();
+    // read_dir doesn't give the entries in any particular order
+    files.sort();
     Ok(files)
 }
+// Replay a saved session then print its analysis report. The speed is a multiplier, 1 being real time.
+fn replay_session(path: &str, speed: f64, replay: bool, settings: &Settings) {
+    match Session::load(path) {
+        Ok(session) => {
+            if replay {
+                if let Err(error)=session.replay(speed, settings.color) {
+                    println!("Error replaying the session: {error}");
+                }
+            }
+            session.print_report();
+        },
+        Err(error) => println!("Error reading the session {path}: {error}"),
+    }
+}
+
+// Let the user type as many source files as they want when none was given on the command line
+fn code_mode_loop(mode: TestMode, settings: &Settings) {
+    let mut input_string = String::new();
+    loop {
+        println!("Which source file do you want to type? [src/main.rs]");
+        input_string.clear();
+        stdin().read_line(&mut input_string).unwrap();
+        let file_name=match input_string.trim() {
+            "" => "src/main.rs",
+            file_name => file_name,
+        };
+        if let Err(error)=typing_tutor_code(file_name, mode, settings) {
+            println!("Error reading {file_name}: {error}");
+        }
+        println!("Do you want to type another file? [Y/N]");
+        input_string.clear();
+        stdin().read_line(&mut input_string).unwrap();
+        if !matches!(input_string.trim(), "Y" | "y") {
+            println!("Okay, see you later!");
+            return;
+        }
+    }
+}
+
+// Take the tests one after the other, as long as the user wants to. When the text comes from the standard
+// input, we can't ask anything so every exercise is taken in a row.
+fn take_tests<T>(items: &[T], ask_more: bool, take_test: impl Fn(&T)) {
+    // The first file sample must be passed without question.
+    take_test(&items[0]);
+    let mut index: usize=1;
+    let sample_numbers=items.len(); //This is in case you add more typing sample files
+    let mut input_string = String::new();
+    loop {
+        if !ask_more {
+            if index>=sample_numbers {
+                break;
+            }
+            take_test(&items[index]);
+            index+=1;
+            continue;
+        }
+        // Give the choice to the user to choose more typing tests
+        println!("Do you want to take more tests? [Y/N]");
+        input_string.clear();
+        stdin().read_line(&mut input_string).unwrap();
+        match input_string.trim() {
+            "Y" | "y" => {if index>=sample_numbers{
+                println!("Oops! There is no test sample anymore. See you later!");
+                break;
+            } else { 
+                take_test(&items[index]);
+                index+=1;
+            }
+        },
+            "N" | "n" => {
+                println!("Okay, see you later!");
+                break;
+            },
+            _ => { 
+                println!("Invalid input. Please enter Y or N.");
+        }
+        }
+    }
+}
+
 fn main() {
-    //First, retrieve the sample files
-    match get_typing_samples() {
+    let cli=Cli::parse();
+    let layout=match Layout::load(&cli.layout) {
+        Ok(layout) => layout,
+        Err(error) => {
+            println!("Error reading the keyboard layout {}: {error}", cli.layout);
+            return;
+        },
+    };
+    let settings=Settings {
+        color: !cli.no_color,
+        target_wpm: cli.target_wpm,
+        layout,
+        keyboard: !cli.no_keyboard,
+    };
+    match &cli.command {
+        Some(Command::Replay {session, speed}) => {
+            replay_session(session, speed.max(0.01), true, &settings);
+            return;
+        },
+        Some(Command::Report {session}) => {
+            replay_session(session, 1.0, false, &settings);
+            return;
+        },
+        _ => {},
+    }
+    // A directory given on the command line can hold samples with any name
+    let samples_path=cli.samples.clone().unwrap_or_else(|| ".".into());
+    let prefix=cli.prefix.clone().unwrap_or_else(|| if cli.samples.is_some() {String::new()} else {"typing".to_string()});
+    if let Some(Command::Race {action}) = &cli.command {
+        // The players are named after their session unless they chose a name
+        let default_name=|name: &Option<String>| name.clone()
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_else(|| "player".to_string());
+        let result=match action {
+            RaceAction::Host {port, name} => {
+                match cli.text_source(&samples_path, &prefix).and_then(|source| source.exercises()) {
+                    Ok(exercises) if !exercises.is_empty() => {
+                        // Every player gets the same sample, chosen at random by the host
+                        let text=exercises[fastrand::usize(..exercises.len())].text.clone();
+                        race::host(*port, &default_name(name), text, &settings)
+                    },
+                    Ok(_) => {
+                        println!("No typing sample files found! Please add files starting with 'typing' to the home project directory.");
+                        return;
+                    },
+                    Err(error) => Err(error),
+                }
+            },
+            RaceAction::Join {address, name} => race::join(address, &default_name(name), &settings),
+        };
+        if let Err(error)=result {
+            println!("Race error: {error}");
+        }
+        return;
+    }
+    if cli.list {
+        match cli.text_source(&samples_path, &prefix).and_then(|source| source.exercises().map(|exercises| (source, exercises))) {
+            // Sample files are listed by name, the other sources by the beginning of their exercises
+            Ok((TextSource::Files(file_names), _)) => file_names.iter().for_each(|file_name| println!("{file_name}")),
+            Ok((_, exercises)) => exercises.iter().enumerate().for_each(|(index, exercise)| {
+                println!("{}: {}", index+1, exercise.text.chars().take(60).collect::<String>());
+            }),
+            Err(error) => println!("Error reading files: {}", error),
+        }
+        return;
+    }
+    let mode=cli.test_mode().unwrap_or_else(choose_mode);
+    if mode.is_streamed() {
+        // The timed and word count tests never run out of text, so we can repeat them as long as the user wants
+        if let Err(error)=typing_tutor_random_words(mode, &settings) {
+            println!("Error reading {WORDS_FILE}: {error}");
+            return;
+        }
+        let mut input_string = String::new();
+        loop {
+            println!("Do you want to take more tests? [Y/N]");
+            input_string.clear();
+            stdin().read_line(&mut input_string).unwrap();
+            match input_string.trim() {
+                "Y" | "y" => {let _=typing_tutor_random_words(mode, &settings);},
+                "N" | "n" => {
+                    println!("Okay, see you later!");
+                    break;
+                },
+                _ => println!("Invalid input. Please enter Y or N."),
+            }
+        }
+        return;
+    }
+    if let TestMode::Code {..} = mode {
+        if cli.samples.is_none() {
+            code_mode_loop(mode, &settings);
+            return;
+        }
+        match get_typing_samples(&samples_path, &prefix) {
+            Ok(mut file_names) if !file_names.is_empty() => {
+                if cli.shuffle {
+                    fastrand::shuffle(&mut file_names);
+                }
+                take_tests(&file_names, true, |file_name| {
+                    if let Err(error)=typing_tutor_code(file_name, mode, &settings) {
+                        println!("Error reading {file_name}: {error}");
+                    }
+                });
+            },
+            Ok(_) => println!("No source files found in {}", samples_path.display()),
+            Err(error) => println!("Error reading files: {}", error),
+        }
+        return;
+    }
+    //First, retrieve the exercises from the sample files or the text source given on the command line
+    match cli.text_source(&samples_path, &prefix).and_then(|source| source.exercises()) {
         // The case where there is no issue reading the sample files
-        Ok(file_names)=>{
-            if file_names.is_empty() {
+        Ok(mut exercises)=>{
+            if exercises.is_empty() {
                 println!("No typing sample files found! Please add files starting with 'typing' to the home project directory.");
                 return;
             }
-            // The first file sample must be passed without question.
-            let _=typing_tutor_per_sample(&file_names[0]);
-            let mut index: usize=1;
-            let sample_numbers=file_names.len(); //This is in case you add more typing sample files
-            let mut input_string = String::new();
-            loop {
-                // Give the choice to the user to choose more typing tests
-                println!("Do you want to take more tests? [Y/N]");
-                input_string.clear();
-                stdin().read_line(&mut input_string).unwrap();
-                let input: String = input_string.trim().to_string();//We convert to string to let it possible to clear it
-                match input.as_str() {
-                    // Always clear the input typed by the user in order not to push characters after each loop
-                    "Y" | "y" => {if index>=sample_numbers{
-                        println!("Oops! There is no test sample anymore. See you later!");
-                        input.to_string().clear();
-                        break;
-                    } else { 
-                        let _=typing_tutor_per_sample(&file_names[index]);
-                        index+=1;
-                        input.to_string().clear();
-                    }
-                },
-                    "N" | "n" => {
-                        println!("Okay, see you later!");
-                        input.to_string().clear();
-                        break;
-                    },
-                    _ => { 
-                        println!("Invalid input. Please enter Y or N.");
-                        input.to_string().clear();
-                }
-                }
+            // Quotes are always given in a random order
+            if cli.shuffle || cli.quotes.is_some() {
+                fastrand::shuffle(&mut exercises);
             }
+            take_tests(&exercises, !cli.stdin, |exercise| {
+                if let Err(error)=typing_tutor_per_sample(exercise, &settings) {
+                    println!("Error during the test: {error}");
+                }
+            });
         },
         // Case where there is an issue reading one of the sample files or more.
         Err(error) => println!("Error reading files: {}", error)
         }
-}
\ No newline at end of file
+}
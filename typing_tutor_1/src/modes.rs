@@ -0,0 +1,120 @@
+// This module holds the alternative test modes to the classic sample file mode: a timed test which ends
+// automatically after a fixed duration and a word count test which ends once a fixed number of random words
+// has been typed. Both modes draw their words from the "words.txt" list. The code practice mode lives in code.rs.
+use std::{fs::read_to_string, io, time::Duration};
+
+pub const WORDS_FILE: &str = "words.txt";
+// The durations in seconds offered to the user for the timed test
+pub const TIMED_DURATIONS: [u64; 4] = [15, 30, 60, 120];
+// Number of words pushed to the screen each time the user gets close to the end of the text
+const CHUNK_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestMode {
+    Sample,          // type the content of a sample file and press Enter when you are done
+    Timed(Duration), // type random words until the time is up
+    Words(usize),    // type a fixed number of random words
+    Code { auto_indent: bool }, // type a source file: Enter inserts a newline and Tab the file's indentation
+}
+
+impl TestMode {
+    // The streamed modes generate their text on the fly instead of reading it from a sample file
+    pub fn is_streamed(&self) -> bool {
+        matches!(self, TestMode::Timed(_) | TestMode::Words(_))
+    }
+}
+
+// Feeds random words to the test as the user progresses so the text on screen never runs out.
+pub struct WordStream {
+    words: Vec<String>,
+    remaining: Option<usize>, // None means there is no limit (timed test)
+}
+
+impl WordStream {
+    pub fn new(file_name: &str, limit: Option<usize>) -> io::Result<Self> {
+        let words: Vec<String> = read_to_string(file_name)?
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        if words.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{file_name} contains no words")));
+        }
+        Ok(Self { words, remaining: limit })
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    // Returns the next chunk of random words, separated by spaces. The first chunk has no leading space.
+    pub fn next_chunk(&mut self, first: bool) -> String {
+        let count = match self.remaining {
+            Some(remaining) => remaining.min(CHUNK_SIZE),
+            None => CHUNK_SIZE,
+        };
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= count;
+        }
+        let chunk: Vec<&str> = (0..count)
+            .map(|_| self.words[fastrand::usize(..self.words.len())].as_str())
+            .collect();
+        if first || chunk.is_empty() {
+            chunk.join(" ")
+        } else {
+            format!(" {}", chunk.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::{remove_file, write}};
+
+    fn stream(name: &str, words: &str, limit: Option<usize>) -> io::Result<WordStream> {
+        let path = temp_dir().join(format!("words_{name}_{}.txt", std::process::id()));
+        write(&path, words).unwrap();
+        let stream = WordStream::new(path.to_str().unwrap(), limit);
+        remove_file(&path).unwrap();
+        stream
+    }
+
+    #[test]
+    fn word_count_stream_is_cut_in_chunks_until_exhausted() {
+        let mut stream = stream("count", "one two three", Some(25)).unwrap();
+        let first = stream.next_chunk(true);
+        assert!(!first.starts_with(' '));
+        assert_eq!(first.split_whitespace().count(), CHUNK_SIZE);
+        let second = stream.next_chunk(false);
+        assert!(second.starts_with(' '));
+        assert_eq!(second.split_whitespace().count(), CHUNK_SIZE);
+        assert!(!stream.is_exhausted());
+        assert_eq!(stream.next_chunk(false).split_whitespace().count(), 5);
+        assert!(stream.is_exhausted());
+        assert_eq!(stream.next_chunk(false), "");
+    }
+
+    #[test]
+    fn timed_stream_never_runs_out() {
+        let mut stream = stream("timed", "one two three", None).unwrap();
+        for _ in 0..100 {
+            let chunk = stream.next_chunk(false);
+            assert!(chunk.split_whitespace().all(|word| ["one", "two", "three"].contains(&word)));
+        }
+        assert!(!stream.is_exhausted());
+    }
+
+    #[test]
+    fn word_list_without_words_is_refused() {
+        let error = stream("empty", " \n", Some(10)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn only_timed_and_word_count_modes_are_streamed() {
+        assert!(TestMode::Timed(Duration::from_secs(15)).is_streamed());
+        assert!(TestMode::Words(10).is_streamed());
+        assert!(!TestMode::Sample.is_streamed());
+        assert!(!TestMode::Code { auto_indent: true }.is_streamed());
+    }
+}
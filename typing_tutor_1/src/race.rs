@@ -0,0 +1,380 @@
+// Multiplayer typing race over the local network. One instance hosts the lobby on a TCP port and the others join
+// it. Every message is a line of json. The host relays the progress of each player to the others, plays the race
+// as well and sends the rankings once everybody is done.
+use crossterm::{event::{poll, read, Event, KeyCode, KeyEventKind}, execute, terminal::{Clear, ClearType}};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io::{self, stdin, stdout, BufRead, BufReader, Write}, net::{TcpListener, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver, Sender}, Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use crate::{draw_text, modes::TestMode, typing_session::{TypingRules, TypingSession}, Settings};
+
+pub const DEFAULT_PORT: u16 = 7878;
+const PROGRESS_BAR_WIDTH: usize = 30;
+// Time given to a player who connected to send its name
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Message {
+    Join { name: String },                                 // A player asks to join the lobby
+    Welcome { name: String },                              // The name given by the host, made unique
+    Lobby { players: Vec<String> },                        // Players waiting for the race
+    Start { text: String, players: Vec<String> },          // Everybody types the same text
+    Progress { name: String, typed: usize },               // Number of characters typed by a player
+    Finished { name: String, wpm: f32, accuracy: f32 },    // A player typed the whole text
+    Left { name: String },                                 // A player quit the race
+    Rankings { results: Vec<RaceResult> },                 // Final results, best first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RaceResult {
+    name: String,
+    wpm: f32,
+    accuracy: f32,
+}
+
+fn send(mut stream: &TcpStream, message: &Message) -> io::Result<()> {
+    let mut line = serde_json::to_string(message).map_err(io::Error::other)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+// Read the messages of a connection in the background. None is sent once the connection is closed.
+fn spawn_reader<T: Clone + Send + 'static>(reader: BufReader<TcpStream>, sender: Sender<(T, Option<Message>)>, id: T) {
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else { break };
+            if let Ok(message) = serde_json::from_str::<Message>(&line) {
+                if sender.send((id.clone(), Some(message))).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = sender.send((id, None));
+    });
+}
+
+struct Connection {
+    name: String,
+    stream: TcpStream,
+}
+
+// Either side of the race: the host talks to every connection, a guest only to the host
+enum Role {
+    Host {
+        connections: Arc<Mutex<Vec<Connection>>>,
+        receiver: Receiver<(String, Option<Message>)>,
+    },
+    Guest {
+        stream: TcpStream,
+        receiver: Receiver<((), Option<Message>)>,
+    },
+}
+
+impl Role {
+    // The host sends a message to every guest except the one it comes from, a guest sends it to the host
+    fn send(&self, message: &Message, except: Option<&str>) {
+        match self {
+            Role::Host { connections, .. } => {
+                for connection in connections.lock().unwrap().iter() {
+                    if Some(connection.name.as_str()) != except {
+                        let _ = send(&connection.stream, message);
+                    }
+                }
+            },
+            Role::Guest { stream, .. } => {let _ = send(stream, message);},
+        }
+    }
+
+    // Messages received since the last call. The host relays what the guests send to the other guests.
+    fn receive(&self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        match self {
+            Role::Host { connections, receiver } => {
+                for (name, message) in receiver.try_iter() {
+                    let message = match message {
+                        Some(message @ (Message::Progress { .. } | Message::Finished { .. })) => message,
+                        Some(_) => continue,
+                        None => {
+                            connections.lock().unwrap().retain(|connection| connection.name != name);
+                            Message::Left { name: name.clone() }
+                        },
+                    };
+                    self.send(&message, Some(&name));
+                    messages.push(message);
+                }
+            },
+            Role::Guest { receiver, .. } => {
+                for (_, message) in receiver.try_iter() {
+                    // The host closed the connection: the race cannot go on
+                    messages.push(message.unwrap_or(Message::Left { name: String::new() }));
+                }
+            },
+        }
+        messages
+    }
+}
+
+#[derive(Default)]
+struct PlayerProgress {
+    typed: usize,
+    result: Option<RaceResult>,
+    left: bool,
+}
+
+fn unique_name(name: &str, taken: &[String]) -> String {
+    let mut candidate = name.to_string();
+    let mut index = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{name} ({index})");
+        index += 1;
+    }
+    candidate
+}
+
+// Welcome a player who connected to the lobby, once it sent its name
+fn handshake(stream: TcpStream, connections: &Mutex<Vec<Connection>>, started: &AtomicBool,
+    sender: Sender<(String, Option<Message>)>, host_name: &str) {
+    let Ok(reader_stream) = stream.try_clone() else { return };
+    if stream.set_read_timeout(Some(JOIN_TIMEOUT)).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(reader_stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() || stream.set_read_timeout(None).is_err() {
+        return;
+    }
+    let Ok(Message::Join { name }) = serde_json::from_str::<Message>(&line) else { return };
+    let mut connections = connections.lock().unwrap();
+    if started.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut players: Vec<String> = connections.iter().map(|connection| connection.name.clone()).collect();
+    players.insert(0, host_name.to_string());
+    let name = unique_name(&name, &players);
+    let _ = send(&stream, &Message::Welcome { name: name.clone() });
+    players.push(name.clone());
+    println!("{name} joined the race ({} players)", players.len());
+    connections.push(Connection { name: name.clone(), stream });
+    for connection in connections.iter() {
+        let _ = send(&connection.stream, &Message::Lobby { players: players.clone() });
+    }
+    spawn_reader(reader, sender, name);
+}
+
+// Host a race: wait for the players to join until Enter is pressed, then start the race with the given text
+pub fn host(port: u16, name: &str, text: String, settings: &Settings) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let connections: Arc<Mutex<Vec<Connection>>> = Arc::new(Mutex::new(Vec::new()));
+    let (sender, receiver) = channel();
+    let started = Arc::new(AtomicBool::new(false));
+    println!("Hosting a race on port {port}. Other players can join with: typing_tutor race join <your address>:{port}");
+    println!("Press Enter to start the race.");
+    {
+        let connections = Arc::clone(&connections);
+        let started = Arc::clone(&started);
+        let host_name = name.to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // Latecomers are not accepted once the race started
+                if started.load(Ordering::SeqCst) {
+                    continue;
+                }
+                // Each player joins in its own thread so that a client which never says its name doesn't hold the
+                // lobby up for the others
+                let connections = Arc::clone(&connections);
+                let started = Arc::clone(&started);
+                let sender = sender.clone();
+                let host_name = host_name.clone();
+                thread::spawn(move || handshake(stream, &connections, &started, sender, &host_name));
+            }
+        });
+    }
+    let mut input_string = String::new();
+    stdin().read_line(&mut input_string)?;
+    started.store(true, Ordering::SeqCst);
+    let mut players: Vec<String> = connections.lock().unwrap().iter().map(|connection| connection.name.clone()).collect();
+    players.insert(0, name.to_string());
+    let role = Role::Host { connections, receiver };
+    role.send(&Message::Start { text: text.clone(), players: players.clone() }, None);
+    race(&role, name, &text, &players, settings)
+}
+
+// Join the race hosted at the given address and wait for the host to start it
+pub fn join(address: &str, name: &str, settings: &Settings) -> io::Result<()> {
+    let stream = TcpStream::connect(address)?;
+    let (sender, receiver) = channel();
+    send(&stream, &Message::Join { name: name.to_string() })?;
+    spawn_reader(BufReader::new(stream.try_clone()?), sender, ());
+    let mut name = name.to_string();
+    println!("Connected to {address}. Waiting for the host to start the race...");
+    loop {
+        match receiver.recv() {
+            Ok((_, Some(Message::Welcome { name: given_name }))) => name = given_name,
+            Ok((_, Some(Message::Lobby { players }))) => println!("Players in the lobby: {}", players.join(", ")),
+            Ok((_, Some(Message::Start { text, players }))) => {
+                let role = Role::Guest { stream, receiver };
+                return race(&role, &name, &text, &players, settings);
+            },
+            Ok((_, Some(_))) => {},
+            Ok((_, None)) | Err(_) => {
+                println!("The host closed the lobby.");
+                return Ok(());
+            },
+        }
+    }
+}
+
+fn draw_progress(text: &str, me: &str, players: &BTreeMap<String, PlayerProgress>) {
+    let total = text.chars().count().max(1);
+    for (name, progress) in players {
+        let filled = progress.typed.min(total) * PROGRESS_BAR_WIDTH / total;
+        let status = match (&progress.result, progress.left) {
+            (_, true) => "left".to_string(),
+            (Some(result), _) => format!("finished: {:.1} wpm", result.wpm),
+            (None, _) => format!("{}%", progress.typed.min(total) * 100 / total),
+        };
+        let you = if name == me {" (you)"} else {""};
+        println!("[{}{}] {name}{you} {status}", "#".repeat(filled), "-".repeat(PROGRESS_BAR_WIDTH - filled));
+    }
+}
+
+// Best typing speed first, the accuracy deciding between equal speeds
+fn rankings(players: &BTreeMap<String, PlayerProgress>) -> Vec<RaceResult> {
+    let mut results: Vec<RaceResult> = players.values().filter_map(|progress| progress.result.clone()).collect();
+    results.sort_by(|a, b| b.wpm.total_cmp(&a.wpm).then(b.accuracy.total_cmp(&a.accuracy)));
+    results
+}
+
+fn race(role: &Role, me: &str, text: &str, names: &[String], settings: &Settings) -> io::Result<()> {
+    let mut typing = TypingSession::new(text, TestMode::Sample, TypingRules::load(&settings.layout));
+    let mut players: BTreeMap<String, PlayerProgress> = names.iter()
+        .map(|name| (name.clone(), PlayerProgress::default()))
+        .collect();
+    let is_host = matches!(role, Role::Host { .. });
+    let start = Instant::now();
+    let mut redraw = true;
+    loop {
+        for message in role.receive() {
+            redraw = true;
+            match message {
+                Message::Progress { name, typed } => players.entry(name).or_default().typed = typed,
+                Message::Finished { name, wpm, accuracy } => {
+                    let result = RaceResult { name: name.clone(), wpm, accuracy };
+                    players.entry(name).or_default().result = Some(result);
+                },
+                Message::Left { name } if !is_host && name.is_empty() => {
+                    println!("The host left the race.");
+                    return Ok(());
+                },
+                Message::Left { name } => players.entry(name).or_default().left = true,
+                Message::Rankings { results } => {
+                    print_rankings(&results);
+                    return Ok(());
+                },
+                _ => {},
+            }
+        }
+        // The host ends the race once every player still connected is done
+        if is_host && players.values().all(|progress| progress.result.is_some() || progress.left) {
+            let results = rankings(&players);
+            role.send(&Message::Rankings { results: results.clone() }, None);
+            print_rankings(&results);
+            return Ok(());
+        }
+        if redraw {
+            execute!(stdout(), Clear(ClearType::All))?;
+            draw_text(typing.text(), typing.typed(), false, settings.color);
+            draw_progress(text, me, &players);
+            println!("Press Esc to quit the race.");
+            redraw = false;
+        }
+        if !poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key_event) = read()? else { continue };
+        let finished = players.get(me).is_some_and(|progress| progress.result.is_some());
+        if key_event.kind != KeyEventKind::Press || finished {
+            continue;
+        }
+        match key_event.code {
+            KeyCode::Esc => return Ok(()),
+            // Enter doesn't end a race: it is over once the whole text has been typed
+            KeyCode::Backspace | KeyCode::Char(_) => {typing.handle_key(key_event, start.elapsed());},
+            _ => continue,
+        }
+        redraw = true;
+        let typed = typing.typed().chars().count();
+        players.entry(me.to_string()).or_default().typed = typed;
+        role.send(&Message::Progress { name: me.to_string(), typed }, None);
+        if typing.is_fully_typed() {
+            let result = typing.result_at(start.elapsed());
+            let accuracy = result.accuracy();
+            let race_result = RaceResult { name: me.to_string(), wpm: result.typing_speed, accuracy };
+            role.send(&Message::Finished { name: me.to_string(), wpm: race_result.wpm, accuracy }, None);
+            players.entry(me.to_string()).or_default().result = Some(race_result);
+        }
+    }
+}
+
+fn print_rankings(results: &[RaceResult]) {
+    println!("Race over! Rankings:");
+    for (position, result) in results.iter().enumerate() {
+        println!("{}. {} - {:.1} wpm, {:.1}% accuracy", position + 1, result.name, result.wpm, result.accuracy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(name: &str, wpm: f32, accuracy: f32) -> (String, PlayerProgress) {
+        let result = RaceResult { name: name.to_string(), wpm, accuracy };
+        (name.to_string(), PlayerProgress { typed: 10, result: Some(result), left: false })
+    }
+
+    #[test]
+    fn unique_name_numbers_the_names_taken() {
+        let taken = vec!["Ann".to_string(), "Bob".to_string(), "Bob (2)".to_string()];
+        assert_eq!(unique_name("Cid", &taken), "Cid");
+        assert_eq!(unique_name("Ann", &taken), "Ann (2)");
+        assert_eq!(unique_name("Bob", &taken), "Bob (3)");
+    }
+
+    #[test]
+    fn rankings_sort_by_speed_then_accuracy() {
+        let mut players = BTreeMap::from([finished("a", 50.0, 90.0), finished("b", 70.0, 80.0), finished("c", 50.0, 99.0)]);
+        // The players who left or are still typing are not ranked
+        players.insert("d".to_string(), PlayerProgress { left: true, ..PlayerProgress::default() });
+        players.insert("e".to_string(), PlayerProgress::default());
+        let names: Vec<String> = rankings(&players).into_iter().map(|result| result.name).collect();
+        assert_eq!(names, vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn handshake_welcomes_a_player_under_a_unique_name() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        send(&client, &Message::Join { name: "Host".to_string() }).unwrap();
+        let connections = Mutex::new(Vec::new());
+        let (sender, _receiver) = channel();
+        handshake(stream, &connections, &AtomicBool::new(false), sender, "Host");
+        let mut line = String::new();
+        BufReader::new(&client).read_line(&mut line).unwrap();
+        assert!(matches!(serde_json::from_str(&line), Ok(Message::Welcome { name }) if name == "Host (2)"));
+        assert_eq!(connections.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn handshake_refuses_players_once_the_race_started() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        send(&client, &Message::Join { name: "Late".to_string() }).unwrap();
+        let connections = Mutex::new(Vec::new());
+        let (sender, _receiver) = channel();
+        handshake(stream, &connections, &AtomicBool::new(true), sender, "Host");
+        assert!(connections.lock().unwrap().is_empty());
+    }
+}
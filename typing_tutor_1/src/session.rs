@@ -0,0 +1,299 @@
+// This module records every keypress of a test with its timestamp so that the session can be saved, replayed
+// later in the terminal and analysed (latency per key, hesitation points and burst speed).
+use crossterm::{event::{KeyCode, KeyEvent, KeyModifiers}, execute, terminal::{Clear, ClearType}};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::{create_dir_all, read_to_string, OpenOptions}, io::{self, stdout, Write},
+    path::{Path, PathBuf}, thread::sleep, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+pub const SESSIONS_DIR: &str = "sessions";
+// A pause longer than this many times the median latency is reported as a hesitation
+const HESITATION_FACTOR: u64 = 3;
+// Number of consecutive keystrokes used to measure the burst speed
+const BURST_WINDOW: usize = 10;
+
+// Name under which a key is saved. We don't rely on the Display implementation of KeyCode because it
+// changes from one platform to another (Backspace is shown as "Delete" on macOS per instance).
+pub fn key_name(key_event: &KeyEvent) -> String {
+    // Keys pressed with Ctrl are commands (Ctrl+D ends the code practice) and must not be replayed as text
+    if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+        if let KeyCode::Char(c) = key_event.code {
+            return format!("Ctrl+{c}");
+        }
+    }
+    match key_event.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystroke {
+    pub key: String,    // The character typed, or the name of a special key such as "Backspace"
+    pub offset_ms: u64, // Time elapsed since the beginning of the test
+    pub correct: bool,  // Whether the key typed the expected character
+    // The text inserted by the key when it is not the key itself, like the indentation added by Tab or Enter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inserted: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub text: String,
+    pub keystrokes: Vec<Keystroke>,
+}
+
+impl Session {
+    pub fn record(&mut self, key: String, offset: Duration, correct: bool) {
+        self.keystrokes.push(Keystroke { key, offset_ms: offset.as_millis() as u64, correct, inserted: None });
+    }
+
+    // Attach the text inserted by the last keystroke, needed to replay the code practice sessions
+    pub fn set_inserted(&mut self, text: String) {
+        if let Some(keystroke) = self.keystrokes.last_mut() {
+            keystroke.inserted = Some(text);
+        }
+    }
+
+    // Save the session as json in the sessions folder and return the path of the new file
+    pub fn save(&self) -> io::Result<PathBuf> {
+        self.save_in(Path::new(SESSIONS_DIR))
+    }
+
+    // The file is named after the time in milliseconds. A session never overwrites another one: when the name is
+    // taken, a number is added to it.
+    fn save_in(&self, dir: &Path) -> io::Result<PathBuf> {
+        create_dir_all(dir)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let mut attempt = 1;
+        loop {
+            let name = if attempt == 1 {format!("session_{timestamp}.json")} else {format!("session_{timestamp}_{attempt}.json")};
+            let path = dir.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(json.as_bytes())?;
+                    return Ok(path);
+                },
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let content = read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    // The text typed after each keystroke, with the time of the keystroke. A "Down" keystroke means the next
+    // character was composed with the previous one (accented letters), so it replaces the last character instead
+    // of being pushed.
+    fn frames(&self) -> Vec<(u64, String)> {
+        let mut typed = String::new();
+        let mut composing = false;
+        self.keystrokes.iter().map(|keystroke| {
+            if let Some(inserted) = &keystroke.inserted {
+                typed.push_str(inserted);
+            }
+            match keystroke.key.as_str() {
+                _ if keystroke.inserted.is_some() => {},
+                "Backspace" => {typed.pop();},
+                "Down" => composing = true,
+                key if key.chars().count() == 1 => {
+                    if composing {
+                        typed.pop();
+                        composing = false;
+                    }
+                    typed.push_str(key);
+                },
+                _ => {}
+            }
+            (keystroke.offset_ms, typed.clone())
+        }).collect()
+    }
+
+    // Re-render the session keystroke by keystroke. A speed of 2.0 replays the session twice as fast.
+    pub fn replay(&self, speed: f64, color: bool) -> io::Result<()> {
+        let mut previous_offset = 0;
+        for (offset_ms, typed) in self.frames() {
+            let wait = offset_ms.saturating_sub(previous_offset) as f64 / speed;
+            sleep(Duration::from_millis(wait as u64));
+            previous_offset = offset_ms;
+            execute!(stdout(), Clear(ClearType::All))?;
+            println!("Replaying at x{speed} speed");
+            crate::draw_text(&self.text, &typed, false, color);
+        }
+        Ok(())
+    }
+
+    // Time elapsed before each keystroke, the first one being measured from the start of the test
+    fn latencies(&self) -> Vec<u64> {
+        let mut previous_offset = 0;
+        self.keystrokes.iter().map(|keystroke| {
+            let latency = keystroke.offset_ms.saturating_sub(previous_offset);
+            previous_offset = keystroke.offset_ms;
+            latency
+        }).collect()
+    }
+
+    // Average latency per character key with its number of presses, slowest keys first
+    fn key_latencies(&self, latencies: &[u64]) -> Vec<(&str, u64, u64)> {
+        let mut per_key: HashMap<&str, (u64, u64)> = HashMap::new();
+        for (keystroke, latency) in self.keystrokes.iter().zip(latencies) {
+            if keystroke.key.chars().count() == 1 {
+                let entry = per_key.entry(keystroke.key.as_str()).or_insert((0, 0));
+                entry.0 += latency;
+                entry.1 += 1;
+            }
+        }
+        let mut per_key: Vec<(&str, u64, u64)> = per_key.into_iter()
+            .map(|(key, (total, count))| (key, total / count, count))
+            .collect();
+        per_key.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        per_key
+    }
+
+    // Hesitations are the pauses much longer than the usual rhythm of the user. Returns the median latency and,
+    // for each hesitation, the key typed after it, its position in the text and the pause.
+    fn hesitations(&self, latencies: &[u64]) -> (u64, Vec<(&str, usize, u64)>) {
+        let mut sorted_latencies = latencies.to_vec();
+        sorted_latencies.sort();
+        let median = sorted_latencies.get(sorted_latencies.len() / 2).copied().unwrap_or(0);
+        let mut hesitations = Vec::new();
+        let mut position: usize = 0;
+        for (keystroke, latency) in self.keystrokes.iter().zip(latencies) {
+            if median > 0 && *latency > median * HESITATION_FACTOR {
+                hesitations.push((keystroke.key.as_str(), position, *latency));
+            }
+            match keystroke.key.as_str() {
+                "Backspace" => position = position.saturating_sub(1),
+                key if key.chars().count() == 1 => position += 1,
+                _ => {}
+            }
+        }
+        (median, hesitations)
+    }
+
+    // The burst speed is the best typing speed reached over a few consecutive correct keystrokes, counting five
+    // characters per word as usual.
+    fn burst_speed(&self) -> f64 {
+        let correct_offsets: Vec<u64> = self.keystrokes.iter()
+            .filter(|keystroke| keystroke.correct)
+            .map(|keystroke| keystroke.offset_ms)
+            .collect();
+        correct_offsets.windows(BURST_WINDOW)
+            .filter_map(|window| {
+                let span_ms = window[BURST_WINDOW - 1] - window[0];
+                (span_ms > 0).then(|| (BURST_WINDOW - 1) as f64 / 5.0 / (span_ms as f64 / 60000.0))
+            })
+            .fold(0.0, f64::max)
+    }
+
+    pub fn print_report(&self) {
+        let latencies = self.latencies();
+        if latencies.is_empty() {
+            println!("This session contains no keystrokes.");
+            return;
+        }
+        println!("Average latency per key:");
+        for (key, average, count) in self.key_latencies(&latencies) {
+            println!("  '{key}': {average} ms ({count} presses)");
+        }
+        let (median, hesitations) = self.hesitations(&latencies);
+        println!("Hesitation points (pauses over {HESITATION_FACTOR}x the median of {median} ms):");
+        for (key, position, latency) in hesitations {
+            println!("  before '{key}' at character {position}: {latency} ms");
+        }
+        println!("Burst speed: {:.1} wpm over {BURST_WINDOW} correct keystrokes", self.burst_speed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{modes::TestMode, typing_session::{TypingRules, TypingSession}};
+
+    // A session whose keystrokes come every given number of milliseconds
+    fn session(keys: &[(&str, u64, bool)]) -> Session {
+        let mut session = Session::default();
+        let mut offset = 0;
+        for (key, latency, correct) in keys {
+            offset += latency;
+            session.record(key.to_string(), Duration::from_millis(offset), *correct);
+        }
+        session
+    }
+
+    #[test]
+    fn latencies_are_measured_from_the_previous_keystroke() {
+        let session = session(&[("a", 200, true), ("b", 100, true), ("c", 300, true)]);
+        assert_eq!(session.latencies(), vec![200, 100, 300]);
+    }
+
+    #[test]
+    fn key_latencies_average_the_character_keys_slowest_first() {
+        let session = session(&[("a", 100, true), ("b", 300, true), ("a", 300, true), ("Backspace", 900, true), ("c", 200, true)]);
+        let latencies = session.latencies();
+        assert_eq!(session.key_latencies(&latencies), vec![("b", 300, 1), ("a", 200, 2), ("c", 200, 1)]);
+    }
+
+    #[test]
+    fn hesitations_are_pauses_over_three_times_the_median() {
+        let session = session(&[("a", 100, true), ("b", 100, true), ("Backspace", 100, true), ("c", 700, true), ("d", 100, true)]);
+        let latencies = session.latencies();
+        // The backspace takes the cursor back, so "c" is typed at the position of "b"
+        assert_eq!(session.hesitations(&latencies), (100, vec![("c", 1, 700)]));
+    }
+
+    #[test]
+    fn no_hesitation_without_rhythm() {
+        let session = session(&[("a", 0, true), ("b", 0, true), ("c", 500, true)]);
+        let latencies = session.latencies();
+        assert_eq!(session.hesitations(&latencies), (0, vec![]));
+    }
+
+    #[test]
+    fn burst_speed_is_the_fastest_window_of_correct_keystrokes() {
+        // Ten correct keystrokes 100 ms apart: 9 characters in 900 ms, 120 wpm
+        let mut keys = vec![("x", 1000, false)];
+        keys.extend(std::iter::repeat_n(("a", 100, true), BURST_WINDOW));
+        assert!((session(&keys).burst_speed() - 120.0).abs() < 1e-9);
+        // Fewer correct keystrokes than the window give no burst
+        assert_eq!(session(&[("a", 100, true)]).burst_speed(), 0.0);
+    }
+
+    #[test]
+    fn replay_types_what_the_live_run_typed() {
+        let rules = TypingRules { e_variants: vec!['é'], diacritical_marks: vec!["0301".to_string()] };
+        let mut live = TypingSession::new("ée ea", TestMode::Sample, rules);
+        let keys = [KeyCode::Char('é'), KeyCode::Char('e'), KeyCode::Char(' '), KeyCode::Char('x'), KeyCode::Backspace,
+            KeyCode::Char('e'), KeyCode::Char('a'), KeyCode::Char('z')];
+        for (index, code) in keys.into_iter().enumerate() {
+            live.handle_key(KeyEvent::new(code, KeyModifiers::NONE), Duration::from_millis(index as u64 * 100));
+        }
+        let typed = live.typed().to_string();
+        assert_eq!(typed, "|| |a");
+        let frames = live.into_recording().frames();
+        assert_eq!(frames.last().map(|(_, typed)| typed.as_str()), Some(typed.as_str()));
+    }
+
+    #[test]
+    fn sessions_saved_at_once_dont_overwrite_each_other() {
+        let dir = std::env::temp_dir().join(format!("sessions_{}", std::process::id()));
+        let first = session(&[("a", 100, true)]);
+        let second = session(&[("b", 100, false)]);
+        let paths = [first.save_in(&dir).unwrap(), second.save_in(&dir).unwrap(), first.save_in(&dir).unwrap()];
+        assert_ne!(paths[0], paths[1]);
+        assert_ne!(paths[1], paths[2]);
+        assert_ne!(paths[0], paths[2]);
+        let keys: Vec<String> = paths.iter()
+            .map(|path| Session::load(path.to_str().unwrap()).unwrap().keystrokes[0].key.clone())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(keys, ["a", "b", "a"]);
+    }
+}
@@ -0,0 +1,298 @@
+// This module turns the different kinds of text the tutor can read into exercises: the sample files, the
+// standard input, Markdown or plain text documents split by paragraph and quote files. Every text is normalized
+// so that it can be typed on a normal keyboard.
+use std::{fs::read_to_string, io::{self, stdin, Read}, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Exercise {
+    pub text: String,
+    pub attribution: Option<String>, // Author of a quote, shown once the test is over
+}
+
+#[derive(Debug, Clone)]
+pub enum TextSource {
+    Files(Vec<String>), // One exercise per file, like the checked-in typing samples
+    Stdin,              // Paragraphs read from the standard input
+    Document(PathBuf),  // Paragraphs of a Markdown (.md) or plain text file
+    Quotes(PathBuf),    // One quote per line followed by its attribution
+}
+
+impl TextSource {
+    pub fn exercises(&self) -> io::Result<Vec<Exercise>> {
+        let exercises = match self {
+            TextSource::Files(file_names) => {
+                let mut exercises = Vec::new();
+                for file_name in file_names {
+                    exercises.push(Exercise { text: normalize(&read_to_string(file_name)?), attribution: None });
+                }
+                exercises
+            },
+            TextSource::Stdin => {
+                let mut content = String::new();
+                stdin().read_to_string(&mut content)?;
+                paragraphs(&content, false)
+            },
+            TextSource::Document(path) => {
+                let markdown = path.extension().is_some_and(|extension| extension == "md" || extension == "markdown");
+                paragraphs(&read_to_string(path)?, markdown)
+            },
+            TextSource::Quotes(path) => read_to_string(path)?.lines().filter_map(parse_quote).collect(),
+        };
+        Ok(exercises.into_iter().filter(|exercise| !exercise.text.is_empty()).collect())
+    }
+}
+
+// Replace the typographic characters which are not on a keyboard and collapse every run of whitespace
+// (new lines included) into a single space.
+pub fn normalize(text: &str) -> String {
+    let replaced: String = text.chars().map(|c| match c {
+        '‘' | '’' | '‚' | '‛' | '′' => "'".to_string(),
+        '“' | '”' | '„' | '‟' | '″' | '«' | '»' => "\"".to_string(),
+        '–' | '—' | '‐' | '‑' | '−' => "-".to_string(),
+        '…' => "...".to_string(),
+        c if c.is_whitespace() => " ".to_string(),
+        c => c.to_string(),
+    }).collect();
+    replaced.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Split a Markdown or plain text document into exercises, one per paragraph. In Markdown, the code blocks are
+// skipped and the syntax is removed so that only the text has to be typed. Plain text is kept as it is.
+fn paragraphs(content: &str, markdown: bool) -> Vec<Exercise> {
+    let mut exercises = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if markdown && trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if trimmed.is_empty() {
+            if !current.is_empty() {
+                exercises.push(Exercise { text: normalize(&current.join(" ")), attribution: None });
+                current.clear();
+            }
+        } else {
+            current.push(if markdown {strip_markdown(trimmed)} else {trimmed.to_string()});
+        }
+    }
+    if !current.is_empty() {
+        exercises.push(Exercise { text: normalize(&current.join(" ")), attribution: None });
+    }
+    exercises
+}
+
+// Remove the heading and list markers, the emphasis and the link targets of a Markdown line
+fn strip_markdown(line: &str) -> String {
+    let mut line = line.trim_start_matches('#').trim_start_matches('>').trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            line = rest;
+        }
+    }
+    // Numbered lists such as "1. "
+    if let Some((number, rest)) = line.split_once(". ") {
+        if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+            line = rest;
+        }
+    }
+    strip_inline(&line.chars().collect::<Vec<char>>())
+}
+
+// Keep the text of the code spans, the emphasis and the [text](url) links. A delimiter without its pair is text,
+// like the underscores of snake_case or the star of 2*3.
+fn strip_inline(chars: &[char]) -> String {
+    let mut output = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let run = run_length(chars, index);
+        let closing = match c {
+            '`' => closing_run(chars, index + run, run),
+            // An underscore opens emphasis only at the start of a word
+            '*' | '_' if chars.get(index + run).is_some_and(|next| !next.is_whitespace())
+                && (c == '*' || index == 0 || !chars[index - 1].is_alphanumeric()) => {
+                closing_run(chars, index + run, run)
+                    .filter(|end| c == '*' || !chars.get(end + run).is_some_and(|next| next.is_alphanumeric()))
+            },
+            '[' => {
+                if let Some((text_end, link_end)) = link(chars, index) {
+                    output.push_str(&strip_inline(&chars[index + 1..text_end]));
+                    index = link_end + 1;
+                    continue;
+                }
+                None
+            },
+            _ => None,
+        };
+        match closing {
+            // The code is typed as it is, the emphasized text may hold more Markdown
+            Some(end) => {
+                if c == '`' {
+                    output.extend(&chars[index + run..end]);
+                } else {
+                    output.push_str(&strip_inline(&chars[index + run..end]));
+                }
+                index = end + run;
+            },
+            // The whole run of an unpaired delimiter is text, so that its end doesn't open another one
+            None if matches!(c, '`' | '*' | '_') => {
+                output.extend(&chars[index..index + run]);
+                index += run;
+            },
+            None => {
+                output.push(c);
+                index += 1;
+            },
+        }
+    }
+    output
+}
+
+// Number of times the character at the start repeats
+fn run_length(chars: &[char], start: usize) -> usize {
+    chars[start..].iter().take_while(|c| **c == chars[start]).count()
+}
+
+// Start of the run of delimiters of the same length which closes the one ending at `from`. The emphasis can't be
+// closed after a blank.
+fn closing_run(chars: &[char], from: usize, length: usize) -> Option<usize> {
+    let delimiter = chars[from - 1];
+    let mut index = from;
+    while index < chars.len() {
+        if chars[index] != delimiter {
+            index += 1;
+            continue;
+        }
+        let run = run_length(chars, index);
+        if run == length && index > from && (delimiter == '`' || !chars[index - 1].is_whitespace()) {
+            return Some(index);
+        }
+        index += run;
+    }
+    None
+}
+
+// End of the text and of the target of a [text](url) link starting at `start`
+fn link(chars: &[char], start: usize) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let text_end = (start..chars.len()).find(|index| {
+        match chars[*index] {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {},
+        }
+        depth == 0
+    })?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let link_end = (text_end + 2..chars.len()).find(|index| chars[*index] == ')')?;
+    Some((text_end, link_end))
+}
+
+// A quote line looks like `The quote — Author`. A double dash or a tab can be used instead of the em dash.
+fn parse_quote(line: &str) -> Option<Exercise> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let split = [" — ", " -- ", "\t"].iter()
+        .filter_map(|separator| line.rsplit_once(separator))
+        .next();
+    Some(match split {
+        Some((quote, author)) => Exercise {
+            text: normalize(quote).trim_matches('"').to_string(),
+            attribution: Some(normalize(author)),
+        },
+        None => Exercise { text: normalize(line).trim_matches('"').to_string(), attribution: None },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(exercises: &[Exercise]) -> Vec<&str> {
+        exercises.iter().map(|exercise| exercise.text.as_str()).collect()
+    }
+
+    #[test]
+    fn normalize_replaces_typographic_characters_and_collapses_whitespace() {
+        assert_eq!(normalize("“Don’t”  — wait…\n\tnow"), "\"Don't\" - wait... now");
+        assert_eq!(normalize("« bonjour »"), "\" bonjour \"");
+        assert_eq!(normalize("  \n "), "");
+    }
+
+    #[test]
+    fn paragraphs_are_split_on_blank_lines_and_skip_code_blocks() {
+        let document = "# Title\n\nFirst line\nsame paragraph.\n\n```rust\nfn main() {}\n\nlet x = 1;\n```\n\nLast one.\n";
+        assert_eq!(texts(&paragraphs(document, true)), vec!["Title", "First line same paragraph.", "Last one."]);
+    }
+
+    #[test]
+    fn plain_text_is_kept_as_it_is() {
+        let document = "# not a heading
+```
+snake_case and 2*3 [note]
+
+- last";
+        assert_eq!(texts(&paragraphs(document, false)), vec!["# not a heading ``` snake_case and 2*3 [note]", "- last"]);
+    }
+
+    #[test]
+    fn strip_markdown_removes_headings_lists_and_emphasis() {
+        assert_eq!(strip_markdown("## A *big* heading"), "A big heading");
+        assert_eq!(strip_markdown("- item with `code`"), "item with code");
+        assert_eq!(strip_markdown("* __bold__ item"), "bold item");
+        assert_eq!(strip_markdown("12. numbered item"), "numbered item");
+        assert_eq!(strip_markdown("> quoted"), "quoted");
+        assert_eq!(strip_markdown("See [the docs](https://example.com) now"), "See the docs now");
+        // A sentence ending a number is not a list
+        assert_eq!(strip_markdown("Version 2. is out"), "Version 2. is out");
+    }
+
+    #[test]
+    fn strip_markdown_keeps_unpaired_delimiters() {
+        assert_eq!(strip_markdown("Call snake_case_name or __init__ now"), "Call snake_case_name or init now");
+        assert_eq!(strip_markdown("_one_ and my_var_"), "one and my_var_");
+        assert_eq!(strip_markdown("2*3 = 6 and a * b"), "2*3 = 6 and a * b");
+        assert_eq!(strip_markdown("**not closed* here"), "**not closed* here");
+        assert_eq!(strip_markdown("Type `a_b * c` and ``x`y``"), "Type a_b * c and x`y");
+        assert_eq!(strip_markdown("An odd ` backtick"), "An odd ` backtick");
+    }
+
+    #[test]
+    fn strip_markdown_keeps_brackets_which_are_not_links() {
+        assert_eq!(strip_markdown("A [note] and [1]"), "A [note] and [1]");
+        assert_eq!(strip_markdown("[open and (close)"), "[open and (close)");
+        assert_eq!(strip_markdown("list[0] is [*the* [first]](url) item"), "list[0] is the [first] item");
+        assert_eq!(strip_markdown("[broken](no end"), "[broken](no end");
+    }
+
+    #[test]
+    fn quotes_are_split_from_their_attribution() {
+        let quote = parse_quote("“Simplicity is prerequisite for reliability.” — Edsger Dijkstra").unwrap();
+        assert_eq!(quote.text, "Simplicity is prerequisite for reliability.");
+        assert_eq!(quote.attribution.as_deref(), Some("Edsger Dijkstra"));
+        let quote = parse_quote("Talk is cheap -- Linus Torvalds").unwrap();
+        assert_eq!((quote.text.as_str(), quote.attribution.as_deref()), ("Talk is cheap", Some("Linus Torvalds")));
+        let quote = parse_quote("Less is more\tMies").unwrap();
+        assert_eq!(quote.attribution.as_deref(), Some("Mies"));
+        // The last separator splits, the dashes of the quote stay in it
+        let quote = parse_quote("A — B — Author").unwrap();
+        assert_eq!((quote.text.as_str(), quote.attribution.as_deref()), ("A - B", Some("Author")));
+    }
+
+    #[test]
+    fn quotes_without_attribution_comments_and_blank_lines() {
+        let quote = parse_quote("  \"Just a quote\"  ").unwrap();
+        assert_eq!((quote.text.as_str(), quote.attribution), ("Just a quote", None));
+        assert!(parse_quote("# a comment").is_none());
+        assert!(parse_quote("   ").is_none());
+    }
+}
@@ -0,0 +1,612 @@
+// The typing test itself, free of any terminal input or output. The main loop reads the key events from crossterm
+// and feeds them to a TypingSession with the time elapsed since the beginning of the test. The session updates the
+// text typed so far, records the keystrokes and tells when the test is over, so that the scoring can be unit tested.
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use regex::Regex;
+use std::{collections::HashMap, fs::read_to_string, time::Duration};
+use unicode_normalization::{char::compose, UnicodeNormalization};
+
+use crate::{code::{indent_unit, leading_indent}, layout::Layout, modes::{TestMode, WordStream}, session::{key_name, Session}};
+
+pub const DIACRITICAL_MARKS_FILE: &str = "diacritical_marks.txt";
+// Pattern matching the "e" letters obtained through unicode composition. The instances of "e" which we can type
+// directly from the keyboard depend on its layout (see Layout::e_variants).
+pub const E_PATTERN: &str = r"([eE])\p{M}*";
+
+// What the keyboard lets the user type, read from the layout and the data files before the test
+#[derive(Debug, Clone, Default)]
+pub struct TypingRules {
+    pub e_variants: Vec<char>,          // Accented "e" letters typed directly, scored like a plain e
+    pub diacritical_marks: Vec<String>, // Hex codes of the combining marks which can be typed after the Down key
+}
+
+impl TypingRules {
+    pub fn load(layout: &Layout) -> Self {
+        let diacritical_marks = read_to_string(DIACRITICAL_MARKS_FILE)
+            .map(|content| content.lines().map(|line| line.trim().to_string()).collect())
+            .unwrap_or_default();
+        Self { e_variants: layout.e_variants(), diacritical_marks }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Typing,   // the test goes on
+    Finished, // the test is over and can be scored
+    Aborted,  // the user left the test with Esc
+}
+
+// Results of a test. They are computed the same way whatever the mode so that they can be compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub total_right: usize,
+    pub total_chars: usize,
+    pub elapsed: Duration,
+    pub typing_speed: f32,
+}
+
+impl TestResult {
+    pub fn accuracy(&self) -> f32 {
+        self.total_right as f32 / self.total_chars.max(1) as f32 * 100.0
+    }
+
+    pub fn print(&self, target_wpm: Option<f32>) {
+        println!("you got {} out of {}!", self.total_right, self.total_chars);
+        println!("The test took {:?}", self.elapsed);
+        println!("Typing speed: {} wpm", self.typing_speed);
+        if let Some(target) = target_wpm {
+            if self.typing_speed >= target {
+                println!("Well done, you reached your target of {target} wpm!");
+            } else {
+                println!("{:.1} wpm to go to reach your target of {target} wpm.", target - self.typing_speed);
+            }
+        }
+    }
+}
+
+pub struct TypingSession {
+    mode: TestMode,
+    rules: TypingRules,
+    re_contains_e: Regex,
+    text: String,
+    typed: String,
+    hidden_input: String, // Hex code of the accent typed after the Down key, composed with the previous letter
+    composing: bool,
+    indent: String,                   // Indentation unit inserted by Tab in the code practice mode
+    word_stream: Option<WordStream>,  // Only used by the timed and word count modes to stream the text in
+    mistakes: HashMap<char, usize>,   // Number of mistakes per expected character, used for the keyboard heatmap
+    recording: Session,               // Every keypress with its timestamp, to replay the session afterwards
+    status: Status,
+    finished_at: Option<Duration>,
+}
+
+impl TypingSession {
+    // The text is compared in its composed form (NFC), which is what the keyboard and the Down key composition
+    // produce, whatever the form of the file it comes from.
+    pub fn new(text: &str, mode: TestMode, rules: TypingRules) -> Self {
+        let text: String = text.nfc().collect();
+        let mut session = Self {
+            mode,
+            rules,
+            re_contains_e: Regex::new(E_PATTERN).unwrap(),
+            indent: indent_unit(&text),
+            text,
+            typed: String::new(),
+            hidden_input: String::new(),
+            composing: false,
+            word_stream: None,
+            mistakes: HashMap::new(),
+            recording: Session::default(),
+            status: Status::Typing,
+            finished_at: None,
+        };
+        if let TestMode::Code { auto_indent: true } = mode {
+            session.skip_indent();
+        }
+        session
+    }
+
+    pub fn from_stream(mut word_stream: WordStream, mode: TestMode, rules: TypingRules) -> Self {
+        let text = word_stream.next_chunk(true);
+        let mut session = Self::new(&text, mode, rules);
+        session.word_stream = Some(word_stream);
+        session
+    }
+
+    pub fn mode(&self) -> TestMode {
+        self.mode
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.composing
+    }
+
+    pub fn hidden_input(&self) -> &str {
+        &self.hidden_input
+    }
+
+    pub fn mistakes(&self) -> &HashMap<char, usize> {
+        &self.mistakes
+    }
+
+    // The character expected at the cursor
+    pub fn next_char(&self) -> Option<char> {
+        self.text.chars().nth(self.typed.chars().count())
+    }
+
+    pub fn is_fully_typed(&self) -> bool {
+        self.typed.chars().count() >= self.text.chars().count()
+    }
+
+    // Time left in the timed mode, None in the other modes
+    pub fn time_left(&self, at: Duration) -> Option<Duration> {
+        match self.mode {
+            TestMode::Timed(duration) => Some(duration.saturating_sub(at)),
+            _ => None,
+        }
+    }
+
+    // Let the session know how much time went by. The timed test ends once its duration is reached.
+    pub fn tick(&mut self, at: Duration) -> Status {
+        if let TestMode::Timed(duration) = self.mode {
+            if self.status == Status::Typing && at >= duration {
+                self.finish(duration);
+            }
+        }
+        self.status
+    }
+
+    pub fn handle_key(&mut self, key_event: KeyEvent, at: Duration) -> Status {
+        if self.tick(at) != Status::Typing || key_event.kind != KeyEventKind::Press {
+            return self.status;
+        }
+        // After the Down key, the keys type the hex code of the accent until Enter is pressed. They are not
+        // recorded: only the composed letter is.
+        if self.composing {
+            match key_event.code {
+                KeyCode::Char(c) => self.hidden_input.push(c),
+                KeyCode::Backspace => {self.hidden_input.pop();},
+                KeyCode::Enter => self.compose(at),
+                // Give up the composition, the letter typed before Down is kept as it is
+                KeyCode::Esc => {
+                    self.hidden_input.clear();
+                    self.compose(at);
+                },
+                _ => {}
+            }
+            return self.status;
+        }
+        let code_mode = matches!(self.mode, TestMode::Code { .. });
+        let correct = matches!(key_event.code, KeyCode::Char(c) if self.next_char() == Some(c));
+        self.recording.record(key_name(&key_event), at, correct);
+        match key_event.code {
+            KeyCode::Backspace => {self.typed.pop();},
+            KeyCode::Esc => self.status = Status::Aborted,
+            KeyCode::Char('d') if code_mode && key_event.modifiers.contains(KeyModifiers::CONTROL) => self.finish(at),
+            KeyCode::Char(c) => {
+                self.push_char(c);
+                self.feed();
+            },
+            // In the code practice mode, Enter goes to the next line instead of ending the test
+            KeyCode::Enter if code_mode && !self.is_fully_typed() => {
+                self.typed.push('\n');
+                let mut inserted = String::from("\n");
+                if let TestMode::Code { auto_indent: true } = self.mode {
+                    inserted.push_str(&self.skip_indent());
+                }
+                self.recording.set_inserted(inserted);
+            },
+            KeyCode::Tab if code_mode && !self.is_fully_typed() => {
+                self.typed.push_str(&self.indent);
+                self.recording.set_inserted(self.indent.clone());
+            },
+            KeyCode::Enter => self.finish(at),
+            KeyCode::Down => self.composing = true,
+            _ => {}
+        }
+        if self.status == Status::Typing && (self.is_complete() || (code_mode && self.is_fully_typed())) {
+            self.finish(at);
+        }
+        self.status
+    }
+
+    // The results of the finished test
+    pub fn result(&self) -> Option<TestResult> {
+        self.finished_at.map(|elapsed| self.result_at(elapsed))
+    }
+
+    // The results of the test if it ended now, as the race needs them before the session is finished
+    pub fn result_at(&self, elapsed: Duration) -> TestResult {
+        // In the streamed modes, only the text the user reached is scored. Otherwise the words generated
+        // in advance would lower the score.
+        let scored_text: String = if self.word_stream.is_some() {
+            self.text.chars().take(self.typed.chars().count()).collect()
+        } else {self.text.clone()};
+        let normalized_text: String = scored_text.nfd().collect();
+        // Transform the text according to the regex pattern in order to better compute the score of total_chars
+        let transformed_text = self.re_contains_e
+            .replace_all(&normalized_text, |caps: &regex::Captures| {caps[1].to_lowercase()});
+        // The other accented letters are composed back so that their marks are not counted as extra characters
+        let total_chars = transformed_text.nfc().filter(|b| *b != 'e').count();
+        let total_right = self.typed.chars().zip(scored_text.chars())
+            .filter(|(a, b)| a == b && *a != '|').count();//letter e replaced by "|" should not be taken into account
+        let typing_length = self.typed.split_whitespace().count() as f32;
+        let typing_speed = typing_length / elapsed.as_secs_f32() * 60.0;
+        TestResult { total_right, total_chars, elapsed, typing_speed }
+    }
+
+    // The keystrokes of the test, to be saved once it is over
+    pub fn into_recording(self) -> Session {
+        let mut recording = self.recording;
+        recording.text = self.text;
+        recording
+    }
+
+    fn finish(&mut self, at: Duration) {
+        self.status = Status::Finished;
+        self.finished_at = Some(at);
+    }
+
+    // The word count test is over once every word has been drawn and typed
+    fn is_complete(&self) -> bool {
+        match &self.word_stream {
+            Some(word_stream) => word_stream.is_exhausted() && self.is_fully_typed(),
+            None => false,
+        }
+    }
+
+    // Push more words once the user gets close to the end of the text displayed so far
+    fn feed(&mut self) {
+        if let Some(word_stream) = self.word_stream.as_mut() {
+            let remaining_chars = self.text.chars().count().saturating_sub(self.typed.chars().count());
+            if remaining_chars < 20 && !word_stream.is_exhausted() {
+                let chunk: String = word_stream.next_chunk(false).nfc().collect();
+                self.text.push_str(&chunk);
+            }
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        // keep the typed text from getting longer than the text to type
+        // The text inserted is recorded when it isn't the key itself, so that a replay of the session types the same
+        let Some(expected_char) = self.next_char() else {
+            self.recording.set_inserted(String::new());
+            return;
+        };
+        if expected_char != c {
+            *self.mistakes.entry(expected_char).or_insert(0) += 1;
+        }
+        if self.re_contains_e.is_match(&c.to_string()) || self.rules.e_variants.contains(&c) {
+            self.typed.push('|');//I don't recommend to use a space instead, cause it will
+            //introduce a bias to the way the score and speed are computed
+            self.recording.set_inserted("|".to_string());
+        } else {
+            self.typed.push(c);
+        }
+    }
+
+    // Compose the letter typed before pressing Down with the accent of the hidden input
+    fn compose(&mut self, at: Duration) {
+        self.composing = false;
+        let hex = std::mem::take(&mut self.hidden_input);
+        let Some(last_char) = self.typed.pop() else { return };
+        // When the composition fails, the letter is recorded again so that a replay of the session stays in sync
+        let accented_char = self.accented(last_char, &hex).unwrap_or(last_char);
+        let expected_char = self.next_char();
+        if accented_char != last_char && expected_char == Some(accented_char) {
+            // The mistake counted on the bare letter is corrected by the accent
+            if let Some(count) = self.mistakes.get_mut(&accented_char) {
+                *count -= 1;
+                if *count == 0 {
+                    self.mistakes.remove(&accented_char);
+                }
+            }
+        }
+        self.recording.record(accented_char.to_string(), at, expected_char == Some(accented_char));
+        self.typed.push(accented_char);
+    }
+
+    fn accented(&self, c: char, hex: &str) -> Option<char> {
+        if self.rules.diacritical_marks.iter().any(|mark| mark.eq_ignore_ascii_case(hex)) {
+            // Parse the string into a u32 then convert it into a char
+            let unicode_value = u32::from_str_radix(hex, 16).ok()?;
+            let mark = char::from_u32(unicode_value).unwrap_or('\u{034F}');
+            compose(c, mark)
+        } else {
+            Some(c)
+        }
+    }
+
+    // Push the indentation expected at the cursor so that the user doesn't have to type it (code practice mode)
+    fn skip_indent(&mut self) -> String {
+        let indent = leading_indent(&self.text, self.typed.chars().count());
+        self.typed.push_str(&indent);
+        indent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn rules() -> TypingRules {
+        TypingRules {
+            e_variants: vec!['é', 'è', 'ê'],
+            diacritical_marks: vec!["0300".to_string(), "0301".to_string(), "0308".to_string()],
+        }
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_str(session: &mut TypingSession, text: &str) -> Status {
+        let mut status = Status::Typing;
+        for c in text.chars() {
+            status = session.handle_key(press(KeyCode::Char(c)), SECOND);
+        }
+        status
+    }
+
+    // Type a letter then compose it with the accent of the given hex code through the Down key
+    fn type_accented(session: &mut TypingSession, base: char, hex: &str) {
+        type_str(session, &base.to_string());
+        session.handle_key(press(KeyCode::Down), SECOND);
+        type_str(session, hex);
+        session.handle_key(press(KeyCode::Enter), SECOND);
+    }
+
+    #[test]
+    fn correct_text_is_fully_scored() {
+        let mut session = TypingSession::new("do it now", TestMode::Sample, rules());
+        assert_eq!(type_str(&mut session, "do it now"), Status::Typing);
+        assert_eq!(session.handle_key(press(KeyCode::Enter), 6 * SECOND), Status::Finished);
+        let result = session.result().unwrap();
+        assert_eq!((result.total_right, result.total_chars), (9, 9));
+        assert_eq!(result.elapsed, 6 * SECOND);
+        assert_eq!(result.typing_speed, 30.0);
+        assert!(session.mistakes().is_empty());
+    }
+
+    #[test]
+    fn mistakes_are_counted_per_expected_char() {
+        let mut session = TypingSession::new("abc", TestMode::Sample, rules());
+        type_str(&mut session, "xbx");
+        assert_eq!(session.typed(), "xbx");
+        assert_eq!(session.mistakes().get(&'a'), Some(&1));
+        assert_eq!(session.mistakes().get(&'c'), Some(&1));
+        assert_eq!(session.result_at(SECOND).total_right, 1);
+    }
+
+    #[test]
+    fn backspace_removes_the_last_char() {
+        let mut session = TypingSession::new("abc", TestMode::Sample, rules());
+        type_str(&mut session, "ax");
+        session.handle_key(press(KeyCode::Backspace), SECOND);
+        assert_eq!(session.typed(), "a");
+        assert_eq!(session.next_char(), Some('b'));
+        type_str(&mut session, "bc");
+        assert_eq!(session.result_at(SECOND).total_right, 3);
+        // The mistake stays counted even though it was corrected
+        assert_eq!(session.mistakes().get(&'b'), Some(&1));
+    }
+
+    #[test]
+    fn backspace_on_empty_input_does_nothing() {
+        let mut session = TypingSession::new("abc", TestMode::Sample, rules());
+        assert_eq!(session.handle_key(press(KeyCode::Backspace), SECOND), Status::Typing);
+        assert_eq!(session.typed(), "");
+    }
+
+    #[test]
+    fn input_stops_at_the_end_of_the_text() {
+        let mut session = TypingSession::new("ab", TestMode::Sample, rules());
+        type_str(&mut session, "abcd");
+        assert_eq!(session.typed(), "ab");
+        assert!(session.is_fully_typed());
+    }
+
+    #[test]
+    fn esc_aborts_the_test() {
+        let mut session = TypingSession::new("ab", TestMode::Sample, rules());
+        assert_eq!(session.handle_key(press(KeyCode::Esc), SECOND), Status::Aborted);
+        assert_eq!(session.result(), None);
+        // Nothing happens once the test is over
+        assert_eq!(type_str(&mut session, "a"), Status::Aborted);
+        assert_eq!(session.typed(), "");
+    }
+
+    #[test]
+    fn key_releases_are_ignored() {
+        let mut session = TypingSession::new("ab", TestMode::Sample, rules());
+        let mut release = press(KeyCode::Char('a'));
+        release.kind = KeyEventKind::Release;
+        session.handle_key(release, SECOND);
+        assert_eq!(session.typed(), "");
+    }
+
+    #[test]
+    fn accent_is_composed_with_the_previous_letter() {
+        let mut session = TypingSession::new("là", TestMode::Sample, rules());
+        type_str(&mut session, "l");
+        type_accented(&mut session, 'a', "0300");
+        assert_eq!(session.typed(), "là");
+        assert!(!session.is_composing());
+        // The mistake counted on the bare "a" is corrected by the accent
+        assert!(session.mistakes().is_empty());
+        assert_eq!(session.result_at(SECOND).total_right, 2);
+    }
+
+    #[test]
+    fn accent_hex_code_is_case_insensitive() {
+        let mut session = TypingSession::new("Ä", TestMode::Sample, rules());
+        session.rules.diacritical_marks = vec!["030A".to_string(), "0308".to_string()];
+        type_accented(&mut session, 'A', "0308");
+        assert_eq!(session.typed(), "Ä");
+        session.handle_key(press(KeyCode::Backspace), SECOND);
+        type_accented(&mut session, 'A', "030a");
+        assert_eq!(session.typed(), "Å");
+    }
+
+    #[test]
+    fn unknown_accent_keeps_the_letter() {
+        let mut session = TypingSession::new("à", TestMode::Sample, rules());
+        type_accented(&mut session, 'a', "0327");
+        assert_eq!(session.typed(), "a");
+        assert_eq!(session.mistakes().get(&'à'), Some(&1));
+    }
+
+    #[test]
+    fn impossible_composition_keeps_the_letter() {
+        // There is no precomposed "q" with a grave accent
+        let mut session = TypingSession::new("q", TestMode::Sample, rules());
+        type_accented(&mut session, 'q', "0300");
+        assert_eq!(session.typed(), "q");
+    }
+
+    #[test]
+    fn backspace_while_composing_edits_the_accent_code() {
+        let mut session = TypingSession::new("à", TestMode::Sample, rules());
+        type_str(&mut session, "a");
+        session.handle_key(press(KeyCode::Down), SECOND);
+        assert!(session.is_composing());
+        type_str(&mut session, "0309");
+        session.handle_key(press(KeyCode::Backspace), SECOND);
+        assert_eq!(session.hidden_input(), "030");
+        assert_eq!(session.typed(), "a");
+        type_str(&mut session, "0");
+        assert_eq!(session.handle_key(press(KeyCode::Enter), SECOND), Status::Typing);
+        assert_eq!(session.typed(), "à");
+    }
+
+    #[test]
+    fn esc_while_composing_cancels_the_accent_only() {
+        let mut session = TypingSession::new("à", TestMode::Sample, rules());
+        type_str(&mut session, "a");
+        session.handle_key(press(KeyCode::Down), SECOND);
+        type_str(&mut session, "0300");
+        assert_eq!(session.handle_key(press(KeyCode::Esc), SECOND), Status::Typing);
+        assert!(!session.is_composing());
+        assert_eq!(session.typed(), "a");
+    }
+
+    #[test]
+    fn composition_is_recorded_for_the_replay() {
+        let mut session = TypingSession::new("à", TestMode::Sample, rules());
+        type_accented(&mut session, 'a', "0300");
+        let keys: Vec<(String, bool)> = session.into_recording().keystrokes.into_iter()
+            .map(|keystroke| (keystroke.key, keystroke.correct))
+            .collect();
+        let expected = [("a", false), ("Down", false), ("à", true)];
+        assert_eq!(keys, expected.map(|(key, correct)| (key.to_string(), correct)));
+    }
+
+    #[test]
+    fn decomposed_text_matches_composed_input() {
+        // "à" written as an "a" followed by a combining grave accent
+        let mut session = TypingSession::new("a\u{300}b", TestMode::Sample, rules());
+        assert_eq!(session.text().chars().count(), 2);
+        type_accented(&mut session, 'a', "0300");
+        type_str(&mut session, "b");
+        let result = session.result_at(SECOND);
+        assert_eq!((result.total_right, result.total_chars), (2, 2));
+    }
+
+    #[test]
+    fn e_letters_are_not_scored() {
+        let mut session = TypingSession::new("été", TestMode::Sample, rules());
+        type_str(&mut session, "éte");
+        // Every "e", accented or not, is replaced by a placeholder
+        assert_eq!(session.typed(), "|t|");
+        let result = session.result_at(SECOND);
+        assert_eq!((result.total_right, result.total_chars), (1, 1));
+    }
+
+    #[test]
+    fn decomposed_e_letters_are_not_scored() {
+        let session = TypingSession::new("E\u{301}t\u{e9}", TestMode::Sample, rules());
+        assert_eq!(session.result_at(SECOND).total_chars, 1);
+    }
+
+    #[test]
+    fn e_variants_depend_on_the_layout() {
+        let mut session = TypingSession::new("ë", TestMode::Sample, TypingRules::default());
+        type_str(&mut session, "ë");
+        assert_eq!(session.typed(), "ë");
+        let mut session = TypingSession::new("ë", TestMode::Sample, TypingRules { e_variants: vec!['ë'], ..rules() });
+        type_str(&mut session, "ë");
+        assert_eq!(session.typed(), "|");
+    }
+
+    #[test]
+    fn composing_an_e_keeps_the_placeholder() {
+        let mut session = TypingSession::new("é", TestMode::Sample, rules());
+        type_accented(&mut session, 'e', "0301");
+        assert_eq!(session.typed(), "|");
+    }
+
+    #[test]
+    fn length_is_counted_in_chars_not_bytes() {
+        let mut session = TypingSession::new("ñ", TestMode::Sample, rules());
+        type_str(&mut session, "nn");
+        assert_eq!(session.typed(), "n");
+        let mut session = TypingSession::new("ab", TestMode::Sample, rules());
+        type_str(&mut session, "ññ");
+        assert_eq!(session.typed(), "ññ");
+    }
+
+    #[test]
+    fn timed_test_ends_after_its_duration() {
+        let mut session = TypingSession::new("with words", TestMode::Timed(15 * SECOND), rules());
+        type_str(&mut session, "with");
+        assert_eq!(session.time_left(10 * SECOND), Some(5 * SECOND));
+        assert_eq!(session.tick(10 * SECOND), Status::Typing);
+        // A key pressed after the time is up is not typed
+        assert_eq!(session.handle_key(press(KeyCode::Char(' ')), 16 * SECOND), Status::Finished);
+        assert_eq!(session.typed(), "with");
+        assert_eq!(session.result().unwrap().elapsed, 15 * SECOND);
+    }
+
+    #[test]
+    fn code_mode_inserts_newlines_and_indentation() {
+        let text = "fn main() {\n    go();\n}";
+        let mut session = TypingSession::new(text, TestMode::Code { auto_indent: false }, rules());
+        type_str(&mut session, "fn main() {");
+        session.handle_key(press(KeyCode::Enter), SECOND);
+        session.handle_key(press(KeyCode::Tab), SECOND);
+        type_str(&mut session, "go();");
+        assert_eq!(session.handle_key(press(KeyCode::Enter), SECOND), Status::Typing);
+        assert_eq!(type_str(&mut session, "}"), Status::Finished);
+        assert_eq!(session.typed(), text);
+    }
+
+    #[test]
+    fn code_mode_skips_the_indentation() {
+        let text = "    a\n    b";
+        let mut session = TypingSession::new(text, TestMode::Code { auto_indent: true }, rules());
+        assert_eq!(session.typed(), "    ");
+        type_str(&mut session, "a");
+        session.handle_key(press(KeyCode::Enter), SECOND);
+        assert_eq!(session.typed(), "    a\n    ");
+    }
+
+    #[test]
+    fn ctrl_d_ends_the_code_mode_only() {
+        let ctrl_d = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        let mut session = TypingSession::new("abc", TestMode::Code { auto_indent: false }, rules());
+        assert_eq!(session.handle_key(ctrl_d, SECOND), Status::Finished);
+        let mut session = TypingSession::new("dd", TestMode::Sample, rules());
+        assert_eq!(session.handle_key(ctrl_d, SECOND), Status::Typing);
+        assert_eq!(session.typed(), "d");
+    }
+}
@@ -0,0 +1,330 @@
+// The summaries fetched are saved on disk, by language and title, so that they are opened again without the network
+// while they are fresh, and as a last resort once they aren't. The bookmarks point to saved summaries.
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_to_string, write},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::CurrentArticle;
+
+pub const CACHE_FILE: &str = "articles_cache.json";
+pub const BOOKMARKS_FILE: &str = "bookmarks.json";
+// A summary older than this is fetched again when the network allows it
+const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// The oldest summaries are forgotten beyond this, except the bookmarked ones
+const MAX_ARTICLES: usize = 500;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+// "Paris_Hilton" and "paris hilton" are the same article
+fn same_title(a: &str, b: &str) -> bool {
+    a.replace('_', " ").trim().to_lowercase() == b.replace('_', " ").trim().to_lowercase()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedArticle {
+    pub language: String, // Code of the wiki
+    pub fetched_at: u64, // Unix timestamp
+    pub article: CurrentArticle,
+    // The other titles which led to the article, like "NYC" for "New York City"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl CachedArticle {
+    pub fn is_fresh(&self) -> bool {
+        now().saturating_sub(self.fetched_at) < TTL.as_secs()
+    }
+
+    // How long ago it was fetched, like "3 hours"
+    pub fn age(&self) -> String {
+        let seconds = now().saturating_sub(self.fetched_at);
+        let (count, unit) = match seconds {
+            0..60 => (seconds, "second"),
+            60..3600 => (seconds / 60, "minute"),
+            3600..86400 => (seconds / 3600, "hour"),
+            _ => (seconds / 86400, "day"),
+        };
+        format!("{count} {unit}{}", if count == 1 {""} else {"s"})
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub language: String,
+    pub title: String,
+}
+
+// Loading a file which is missing gives an empty list, like a cache which can't be read
+fn load<T: for<'a> Deserialize<'a>>(path: &Path) -> Vec<T> {
+    read_to_string(path).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save<T: Serialize>(path: &Path, items: &[T]) -> io::Result<()> {
+    write(path, serde_json::to_string_pretty(items).map_err(io::Error::other)?)
+}
+
+#[derive(Default)]
+pub struct Bookmarks {
+    path: PathBuf,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), bookmarks: load(path) }
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn contains(&self, language: &str, title: &str) -> bool {
+        self.bookmarks.iter().any(|bookmark| bookmark.language == language && same_title(&bookmark.title, title))
+    }
+
+    // Returns true when the article is bookmarked, false when its bookmark is removed
+    pub fn toggle(&mut self, language: &str, title: &str) -> io::Result<bool> {
+        let bookmarked = !self.contains(language, title);
+        if bookmarked {
+            self.bookmarks.push(Bookmark { language: language.to_string(), title: title.to_string() });
+        } else {
+            self.bookmarks.retain(|bookmark| !(bookmark.language == language && same_title(&bookmark.title, title)));
+        }
+        save(&self.path, &self.bookmarks)?;
+        Ok(bookmarked)
+    }
+}
+
+#[derive(Default)]
+pub struct ArticleCache {
+    path: PathBuf,
+    articles: Vec<CachedArticle>,
+}
+
+impl ArticleCache {
+    pub fn load(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), articles: load(path) }
+    }
+
+    // The article of this title, or else the latest one the title led to
+    pub fn get(&self, language: &str, title: &str) -> Option<&CachedArticle> {
+        let articles = || self.articles.iter().filter(|cached| cached.language == language);
+        articles().find(|cached| same_title(&cached.article.title, title))
+            .or_else(|| articles().rev().find(|cached| cached.aliases.iter().any(|alias| same_title(alias, title))))
+    }
+
+    // Save the article fetched for the requested title, which is kept as an alias when it isn't the title of the
+    // article, so that a redirect opens it offline as well
+    pub fn insert(&mut self, language: &str, requested: &str, article: &CurrentArticle, bookmarks: &Bookmarks) -> io::Result<()> {
+        let mut aliases = Vec::new();
+        self.articles.retain(|cached| {
+            let replaced = cached.language == language && same_title(&cached.article.title, &article.title);
+            if replaced {
+                aliases.append(&mut cached.aliases.clone());
+            }
+            !replaced
+        });
+        if !same_title(requested, &article.title) && !aliases.iter().any(|alias| same_title(alias, requested)) {
+            aliases.push(requested.to_string());
+        }
+        self.articles.push(CachedArticle { language: language.to_string(), fetched_at: now(), article: article.clone(), aliases });
+        // The articles are kept from the oldest to the latest
+        while self.articles.len() > MAX_ARTICLES {
+            let Some(oldest) = self.articles.iter()
+                .position(|cached| !bookmarks.contains(&cached.language, &cached.article.title)) else {
+                break;
+            };
+            self.articles.remove(oldest);
+        }
+        save(&self.path, &self.articles)
+    }
+
+    // The saved articles with all the words of the query, in their title first, then in their text. The latest
+    // ones come first.
+    pub fn search(&self, query: &str) -> Vec<&CachedArticle> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let matches = |text: &str| {
+            let text = text.to_lowercase();
+            words.iter().all(|word| text.contains(word.as_str()))
+        };
+        let mut found: Vec<(bool, &CachedArticle)> = self.articles.iter().rev()
+            .filter_map(|cached| {
+                let article = &cached.article;
+                if matches(&article.title) {
+                    Some((false, cached))
+                } else if matches(&format!("{} {} {}", article.title, article.description, article.extract)) {
+                    Some((true, cached))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        found.sort_by_key(|(in_text, _)| *in_text);
+        found.into_iter().map(|(_, cached)| cached).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::remove_file};
+
+    fn article(title: &str, extract: &str) -> CurrentArticle {
+        CurrentArticle { title: title.to_string(), description: String::new(), extract: extract.to_string() }
+    }
+
+    fn titles(found: &[&CachedArticle]) -> Vec<String> {
+        found.iter().map(|cached| cached.article.title.clone()).collect()
+    }
+
+    #[test]
+    fn titles_ignore_case_and_underscores() {
+        assert!(same_title("Paris_Hilton", " paris hilton"));
+        assert!(!same_title("Paris", "Paris Hilton"));
+    }
+
+    #[test]
+    fn summaries_are_fresh_for_a_day() {
+        let mut cached = CachedArticle { language: "en".to_string(), fetched_at: now(), article: article("Paris", ""), aliases: Vec::new() };
+        assert!(cached.is_fresh());
+        cached.fetched_at = now() - TTL.as_secs() + 60;
+        assert!(cached.is_fresh());
+        assert_eq!(cached.age(), "23 hours");
+        cached.fetched_at = now() - TTL.as_secs();
+        assert!(!cached.is_fresh());
+        assert_eq!(cached.age(), "1 day");
+    }
+
+    #[test]
+    fn toggles_and_saves_bookmarks() {
+        let path = temp_dir().join(format!("bookmarks_{}.json", std::process::id()));
+        let mut bookmarks = Bookmarks::load(&path);
+        assert!(bookmarks.list().is_empty());
+        assert!(bookmarks.toggle("en", "Paris_Hilton").unwrap());
+        assert!(bookmarks.toggle("fr", "Paris").unwrap());
+        assert!(bookmarks.contains("en", "paris hilton"));
+        assert!(!bookmarks.contains("fr", "Paris Hilton"));
+        assert_eq!(Bookmarks::load(&path).list(), bookmarks.list());
+        // The same title in another form removes the bookmark
+        assert!(!bookmarks.toggle("en", "Paris Hilton").unwrap());
+        let saved = Bookmarks::load(&path);
+        remove_file(&path).unwrap();
+        assert_eq!(saved.list(), [Bookmark { language: "fr".to_string(), title: "Paris".to_string() }]);
+    }
+
+    #[test]
+    fn caches_by_language_and_title() {
+        let path = temp_dir().join(format!("articles_cache_{}.json", std::process::id()));
+        let mut cache = ArticleCache::load(&path);
+        cache.insert("en", "Paris", &article("Paris", "old"), &Bookmarks::default()).unwrap();
+        cache.insert("en", "paris", &article("paris", "new"), &Bookmarks::default()).unwrap();
+        cache.insert("fr", "Paris", &article("Paris", "capitale"), &Bookmarks::default()).unwrap();
+        let saved = ArticleCache::load(&path);
+        remove_file(&path).unwrap();
+        assert_eq!(saved.articles.len(), 2);
+        assert_eq!(saved.get("en", "PARIS").unwrap().article.extract, "new");
+        assert_eq!(saved.get("fr", "Paris").unwrap().article.extract, "capitale");
+        assert!(saved.get("de", "Paris").is_none());
+    }
+
+    #[test]
+    fn redirects_open_the_saved_article() {
+        let path = temp_dir().join(format!("aliases_cache_{}.json", std::process::id()));
+        let mut cache = ArticleCache::load(&path);
+        cache.insert("en", "NYC", &article("New York City", "old"), &Bookmarks::default()).unwrap();
+        cache.insert("en", "Big_Apple", &article("New York City", "new"), &Bookmarks::default()).unwrap();
+        // Opening the article by its own title keeps the titles which led to it
+        cache.insert("en", "New York City", &article("New York City", "latest"), &Bookmarks::default()).unwrap();
+        let saved = ArticleCache::load(&path);
+        remove_file(&path).unwrap();
+        assert_eq!(saved.articles.len(), 1);
+        assert_eq!(saved.articles[0].aliases, ["NYC", "Big_Apple"]);
+        for title in ["nyc", "big apple", "New_York_City"] {
+            assert_eq!(saved.get("en", title).unwrap().article.extract, "latest", "{title}");
+        }
+        assert!(saved.get("fr", "NYC").is_none());
+    }
+
+    #[test]
+    fn the_title_of_an_article_comes_before_the_aliases() {
+        let path = temp_dir().join(format!("titles_cache_{}.json", std::process::id()));
+        let mut cache = ArticleCache::load(&path);
+        cache.insert("en", "Mercury", &article("Mercury (planet)", "planet"), &Bookmarks::default()).unwrap();
+        assert_eq!(cache.get("en", "Mercury").unwrap().article.extract, "planet");
+        cache.insert("en", "Mercury", &article("Mercury", "disambiguation"), &Bookmarks::default()).unwrap();
+        remove_file(&path).unwrap();
+        assert_eq!(cache.get("en", "Mercury").unwrap().article.extract, "disambiguation");
+        assert_eq!(cache.get("en", "Mercury (planet)").unwrap().article.extract, "planet");
+    }
+
+    #[test]
+    fn eviction_keeps_the_bookmarked_articles() {
+        let path = temp_dir().join(format!("evicted_cache_{}.json", std::process::id()));
+        let bookmarks_path = temp_dir().join(format!("evicted_bookmarks_{}.json", std::process::id()));
+        let mut bookmarks = Bookmarks::load(&bookmarks_path);
+        bookmarks.toggle("en", "Article 0").unwrap();
+        let mut cache = ArticleCache::load(&path);
+        for index in 0..MAX_ARTICLES + 2 {
+            cache.articles.push(CachedArticle {
+                language: "en".to_string(),
+                fetched_at: index as u64,
+                article: article(&format!("Article {index}"), ""),
+                aliases: Vec::new(),
+            });
+        }
+        cache.insert("en", "Latest", &article("Latest", ""), &bookmarks).unwrap();
+        remove_file(&path).unwrap();
+        remove_file(&bookmarks_path).unwrap();
+        assert_eq!(cache.articles.len(), MAX_ARTICLES);
+        // The oldest articles which aren't bookmarked are the ones forgotten
+        assert!(cache.get("en", "Article 0").is_some());
+        assert!(cache.get("en", "Article 1").is_none());
+        assert!(cache.get("en", "Article 3").is_none());
+        assert!(cache.get("en", "Article 4").is_some());
+        assert!(cache.get("en", "Latest").is_some());
+    }
+
+    #[test]
+    fn eviction_stops_when_everything_is_bookmarked() {
+        let path = temp_dir().join(format!("bookmarked_cache_{}.json", std::process::id()));
+        let bookmarks_path = temp_dir().join(format!("all_bookmarks_{}.json", std::process::id()));
+        let mut bookmarks = Bookmarks::load(&bookmarks_path);
+        let mut cache = ArticleCache::load(&path);
+        for index in 0..MAX_ARTICLES {
+            let title = format!("Article {index}");
+            cache.articles.push(CachedArticle { language: "en".to_string(), fetched_at: 0, article: article(&title, ""), aliases: Vec::new() });
+            bookmarks.bookmarks.push(Bookmark { language: "en".to_string(), title });
+        }
+        bookmarks.bookmarks.push(Bookmark { language: "en".to_string(), title: "Latest".to_string() });
+        cache.insert("en", "Latest", &article("Latest", ""), &bookmarks).unwrap();
+        remove_file(&path).unwrap();
+        assert_eq!(cache.articles.len(), MAX_ARTICLES + 1);
+    }
+
+    #[test]
+    fn search_ranks_titles_first_then_the_latest() {
+        let mut cache = ArticleCache::default();
+        for (title, extract) in [
+            ("Seine", "River flowing through Paris"),
+            ("Paris", "Capital of France"),
+            ("Rouen", "City on the Seine"),
+            ("Paris Hilton", "American media personality"),
+            ("Louvre", "Museum in PARIS"),
+        ] {
+            cache.articles.push(CachedArticle { language: "en".to_string(), fetched_at: 0, article: article(title, extract), aliases: Vec::new() });
+        }
+        assert_eq!(titles(&cache.search("paris")), ["Paris Hilton", "Paris", "Louvre", "Seine"]);
+        // Every word must be found, in the title or in the text
+        assert_eq!(titles(&cache.search("paris river")), ["Seine"]);
+        assert_eq!(titles(&cache.search("seine")), ["Seine", "Rouen"]);
+        assert!(cache.search("lyon").is_empty());
+    }
+}
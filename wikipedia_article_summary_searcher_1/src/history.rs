@@ -0,0 +1,145 @@
+// The articles opened, in order, to go back and forward like in a browser. Opening an article after going back
+// forgets the ones which were ahead.
+use crate::languages::Language;
+
+// Only the end of a long path is shown
+const MAX_BREADCRUMBS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub language: Language,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Back,
+    Forward,
+}
+
+#[derive(Default)]
+pub struct History {
+    visits: Vec<Visit>,
+    position: usize, // The visit shown, when there is any
+}
+
+impl History {
+    pub fn visit(&mut self, visit: Visit) {
+        // Opening the article shown again isn't a step
+        if self.visits.get(self.position) == Some(&visit) {
+            return;
+        }
+        if !self.visits.is_empty() {
+            self.visits.truncate(self.position + 1);
+        }
+        self.visits.push(visit);
+        self.position = self.visits.len() - 1;
+    }
+
+    // The visit one step back or forward from the one shown, if there is any
+    pub fn peek(&self, step: Step) -> Option<&Visit> {
+        match step {
+            Step::Back => self.position.checked_sub(1).and_then(|position| self.visits.get(position)),
+            Step::Forward => self.visits.get(self.position + 1),
+        }
+    }
+
+    // Move one step, once the visit of that step is shown
+    pub fn step(&mut self, step: Step) {
+        if self.peek(step).is_some() {
+            match step {
+                Step::Back => self.position -= 1,
+                Step::Forward => self.position += 1,
+            }
+        }
+    }
+
+    // The path which led to the article shown, like "Paris > Seine > Le Havre"
+    pub fn breadcrumbs(&self) -> String {
+        let Some(path) = self.visits.get(..=self.position) else {
+            return String::new();
+        };
+        let shown = &path[path.len().saturating_sub(MAX_BREADCRUMBS)..];
+        let crumbs: Vec<&str> = shown.iter().map(|visit| visit.title.as_str()).collect();
+        if shown.len() < path.len() {format!("… > {}", crumbs.join(" > "))} else {crumbs.join(" > ")}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(title: &str) -> Visit {
+        Visit { language: Language { name: "English".to_string(), code: "en".to_string() }, title: title.to_string() }
+    }
+
+    fn history(titles: &[&str]) -> History {
+        let mut history = History::default();
+        for title in titles {
+            history.visit(visit(title));
+        }
+        history
+    }
+
+    fn peek(history: &History, step: Step) -> Option<&str> {
+        history.peek(step).map(|visit| visit.title.as_str())
+    }
+
+    #[test]
+    fn empty_history_has_no_step() {
+        let mut history = History::default();
+        assert_eq!(peek(&history, Step::Back), None);
+        assert_eq!(peek(&history, Step::Forward), None);
+        history.step(Step::Back);
+        history.step(Step::Forward);
+        assert_eq!(history.breadcrumbs(), "");
+    }
+
+    #[test]
+    fn steps_back_and_forward() {
+        let mut history = history(&["Paris", "Seine", "Le Havre"]);
+        assert_eq!(peek(&history, Step::Back), Some("Seine"));
+        assert_eq!(peek(&history, Step::Forward), None);
+        // Peeking doesn't move, only a step does
+        assert_eq!(peek(&history, Step::Back), Some("Seine"));
+        history.step(Step::Back);
+        history.step(Step::Back);
+        assert_eq!(history.breadcrumbs(), "Paris");
+        assert_eq!(peek(&history, Step::Back), None);
+        history.step(Step::Back);
+        assert_eq!(peek(&history, Step::Forward), Some("Seine"));
+        history.step(Step::Forward);
+        history.step(Step::Forward);
+        history.step(Step::Forward);
+        assert_eq!(history.breadcrumbs(), "Paris > Seine > Le Havre");
+    }
+
+    #[test]
+    fn visiting_after_going_back_forgets_what_was_ahead() {
+        let mut history = history(&["Paris", "Seine", "Le Havre"]);
+        history.step(Step::Back);
+        history.visit(visit("Rouen"));
+        assert_eq!(history.breadcrumbs(), "Paris > Seine > Rouen");
+        assert_eq!(peek(&history, Step::Forward), None);
+    }
+
+    #[test]
+    fn opening_the_article_shown_again_is_not_a_step() {
+        let mut history = history(&["Paris", "Seine", "Seine"]);
+        assert_eq!(history.breadcrumbs(), "Paris > Seine");
+        history.step(Step::Back);
+        // Opening the article ahead keeps the visits after it
+        history.visit(visit("Paris"));
+        assert_eq!(peek(&history, Step::Forward), Some("Seine"));
+    }
+
+    #[test]
+    fn long_paths_only_show_their_end() {
+        let mut history = history(&["A", "B", "C", "D", "E"]);
+        assert_eq!(history.breadcrumbs(), "A > B > C > D > E");
+        history.visit(visit("F"));
+        assert_eq!(history.breadcrumbs(), "… > B > C > D > E > F");
+        history.step(Step::Back);
+        assert_eq!(history.breadcrumbs(), "A > B > C > D > E");
+    }
+}
@@ -0,0 +1,217 @@
+// The languages of Wikipedia, read from the "wikipedia_languages" file, and the popup to choose one of them at any
+// time. The list is filtered as you type: the letters have to appear in order in the name or in the code.
+use crossterm::event::KeyCode;
+use csv::Reader;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    widgets::{Block, Borders, Clear, List, ListState, Paragraph},
+    Frame,
+};
+use std::error::Error;
+
+use crate::popup_area;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Language {
+    pub name: String,
+    pub code: String,
+}
+
+// Function to load the "wikipedia_languages" file, sorted by name to be listed in the picker
+pub fn load_languages() -> Result<Vec<Language>, Box<dyn Error>> {
+    let mut languages = Vec::new();
+    let mut reader = Reader::from_path("wikipedia_languages.csv")?;
+    // The reader already skips the first record (headers)
+    for result in reader.records() {
+        let record = result?;
+        if let (Some(name), Some(code)) = (record.get(0), record.get(1)) {
+            languages.push(Language { name: name.trim().to_string(), code: code.trim().to_string() });
+        }
+    }
+    languages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(languages)
+}
+
+// How well the query matches the text, the lower the better: the whole text, then its beginning, then a part of it,
+// then letters in order with the fewest gaps between them. None when the letters can't be found in order.
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase();
+    if text == query {
+        return Some(0);
+    }
+    if text.starts_with(query) {
+        return Some(1);
+    }
+    if text.contains(query) {
+        return Some(2);
+    }
+    let mut gaps = 0;
+    let mut chars = text.chars();
+    for wanted in query.chars() {
+        loop {
+            match chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(3 + gaps)
+}
+
+pub enum Picked {
+    Nothing,
+    Cancelled,
+    Language(Language),
+}
+
+pub struct LanguagePicker {
+    query: String,
+    matches: Vec<Language>,
+    list_state: ListState,
+}
+
+impl LanguagePicker {
+    // The current language is selected when the picker opens
+    pub fn new(languages: &[Language], current: &Language) -> Self {
+        let mut picker = Self { query: String::new(), matches: languages.to_vec(), list_state: ListState::default() };
+        picker.list_state.select(Some(languages.iter().position(|language| language == current).unwrap_or(0)));
+        picker
+    }
+
+    fn filter(&mut self, languages: &[Language]) {
+        let query = self.query.trim().to_lowercase();
+        let mut scored: Vec<(usize, &Language)> = languages.iter()
+            .filter_map(|language| {
+                let score = [fuzzy_score(&query, &language.code), fuzzy_score(&query, &language.name)]
+                    .into_iter().flatten().min()?;
+                Some((score, language))
+            })
+            .collect();
+        // The sort is stable: the languages which match as well stay sorted by name
+        scored.sort_by_key(|(score, _)| *score);
+        self.matches = scored.into_iter().map(|(_, language)| language.clone()).collect();
+        self.list_state.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, languages: &[Language]) -> Picked {
+        match code {
+            KeyCode::Esc => return Picked::Cancelled,
+            KeyCode::Enter => {
+                return match self.list_state.selected().and_then(|index| self.matches.get(index)) {
+                    Some(language) => Picked::Language(language.clone()),
+                    None => Picked::Nothing,
+                }
+            },
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter(languages);
+            },
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.filter(languages);
+            },
+            _ => {},
+        }
+        Picked::Nothing
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let area = popup_area(f.area(), 50, 20);
+        f.render_widget(Clear, area);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let query_block = Block::default().title("Language (Enter: choose, Esc: cancel)").borders(Borders::ALL);
+        f.render_widget(Paragraph::new(self.query.clone()).block(query_block), layout[0]);
+        let items: Vec<String> = self.matches.iter().map(|language| format!("{} ({})", language.name, language.code)).collect();
+        let title = if items.is_empty() {"No language found"} else {"Up/Down to select"};
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().reversed());
+        f.render_stateful_widget(list, layout[1], &mut self.list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(name: &str, code: &str) -> Language {
+        Language { name: name.to_string(), code: code.to_string() }
+    }
+
+    // Sorted by name, like the list loaded from the file
+    fn languages() -> Vec<Language> {
+        vec![
+            language("Deutsch", "de"),
+            language("English", "en"),
+            language("Español", "es"),
+            language("Français", "fr"),
+            language("Frysk", "fy"),
+            language("Nederlands", "nl"),
+        ]
+    }
+
+    fn names(picker: &LanguagePicker) -> Vec<&str> {
+        picker.matches.iter().map(|language| language.name.as_str()).collect()
+    }
+
+    fn type_query(query: &str) -> LanguagePicker {
+        let languages = languages();
+        let mut picker = LanguagePicker::new(&languages, &languages[1]);
+        for c in query.chars() {
+            picker.handle_key(KeyCode::Char(c), &languages);
+        }
+        picker
+    }
+
+    #[test]
+    fn fuzzy_score_ranks_exact_prefix_substring_then_gaps() {
+        assert_eq!(fuzzy_score("english", "English"), Some(0));
+        assert_eq!(fuzzy_score("eng", "English"), Some(1));
+        assert_eq!(fuzzy_score("lish", "English"), Some(2));
+        assert_eq!(fuzzy_score("egs", "English"), Some(3 + 3));
+        assert_eq!(fuzzy_score("ensh", "English"), Some(3 + 3));
+        assert_eq!(fuzzy_score("ehs", "English"), None);
+        assert_eq!(fuzzy_score("englishman", "English"), None);
+    }
+
+    #[test]
+    fn fewer_gaps_rank_higher() {
+        assert_eq!(fuzzy_score("fy", "Frysk"), Some(3 + 1));
+        assert_eq!(fuzzy_score("fk", "Frysk"), Some(3 + 3));
+    }
+
+    #[test]
+    fn picker_matches_the_code_or_the_name() {
+        // "de" is the code of Deutsch, and in the middle of Nederlands
+        assert_eq!(names(&type_query("de")), vec!["Deutsch", "Nederlands"]);
+        // "nl" is the code of Nederlands, and spread in English
+        assert_eq!(names(&type_query("nl")), vec!["Nederlands", "English"]);
+        assert_eq!(names(&type_query("zz")), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn equal_matches_stay_sorted_by_name() {
+        assert_eq!(names(&type_query("f")), vec!["Français", "Frysk"]);
+        // English and Français both contain a "n", after the prefix of Nederlands
+        assert_eq!(names(&type_query("n")), vec!["Nederlands", "English", "Français"]);
+    }
+
+    #[test]
+    fn picker_opens_on_the_current_language_and_picks_the_best_match() {
+        let languages = languages();
+        let mut picker = LanguagePicker::new(&languages, &languages[3]);
+        assert_eq!(picker.list_state.selected(), Some(3));
+        for c in "nl".chars() {
+            picker.handle_key(KeyCode::Char(c), &languages);
+        }
+        assert!(matches!(picker.handle_key(KeyCode::Enter, &languages), Picked::Language(language) if language.code == "nl"));
+        assert!(matches!(picker.handle_key(KeyCode::Esc, &languages), Picked::Cancelled));
+    }
+}
@@ -0,0 +1,121 @@
+// Popup of the articles kept on disk: the bookmarks, or all the saved articles searched by what is typed. Both work
+// without the network.
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    widgets::{Block, Borders, Clear, List, ListState, Paragraph},
+    Frame,
+};
+use std::io;
+
+use crate::{cache::{ArticleCache, Bookmark, Bookmarks}, popup_area};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shelf {
+    Bookmarks,
+    Saved,
+}
+
+pub enum LibraryAction {
+    Stay,
+    Close,
+    Open(Bookmark),
+}
+
+pub struct Library {
+    shelf: Shelf,
+    query: String,
+    list_state: ListState,
+}
+
+impl Library {
+    pub fn new(shelf: Shelf) -> Self {
+        Self { shelf, query: String::new(), list_state: ListState::default().with_selected(Some(0)) }
+    }
+
+    // The articles listed, with the line shown for each of them
+    fn entries(&self, cache: &ArticleCache, bookmarks: &Bookmarks) -> Vec<(Bookmark, String)> {
+        let line = |language: &str, title: &str| {
+            match cache.get(language, title).map(|cached| &cached.article.description).filter(|description| !description.is_empty()) {
+                Some(description) => format!("{title} ({language}) - {description}"),
+                None => format!("{title} ({language})"),
+            }
+        };
+        match self.shelf {
+            Shelf::Bookmarks => {
+                let words: Vec<String> = self.query.split_whitespace().map(str::to_lowercase).collect();
+                bookmarks.list().iter()
+                    .filter(|bookmark| words.iter().all(|word| bookmark.title.to_lowercase().contains(word.as_str())))
+                    .map(|bookmark| (bookmark.clone(), line(&bookmark.language, &bookmark.title)))
+                    .collect()
+            },
+            Shelf::Saved => cache.search(&self.query).into_iter()
+                .map(|cached| {
+                    let bookmark = Bookmark { language: cached.language.clone(), title: cached.article.title.clone() };
+                    let line = line(&bookmark.language, &bookmark.title);
+                    (bookmark, line)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, cache: &ArticleCache, bookmarks: &mut Bookmarks) -> io::Result<LibraryAction> {
+        match code {
+            KeyCode::Esc => return Ok(LibraryAction::Close),
+            KeyCode::Tab => {
+                self.shelf = if self.shelf == Shelf::Bookmarks {Shelf::Saved} else {Shelf::Bookmarks};
+                self.list_state.select(Some(0));
+            },
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Enter => {
+                let entries = self.entries(cache, bookmarks);
+                if let Some((bookmark, _)) = self.list_state.selected().and_then(|index| entries.into_iter().nth(index)) {
+                    return Ok(LibraryAction::Open(bookmark));
+                }
+            },
+            KeyCode::Delete if self.shelf == Shelf::Bookmarks => {
+                let entries = self.entries(cache, bookmarks);
+                if let Some((bookmark, _)) = self.list_state.selected().and_then(|index| entries.into_iter().nth(index)) {
+                    bookmarks.toggle(&bookmark.language, &bookmark.title)?;
+                }
+            },
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.list_state.select(Some(0));
+            },
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.list_state.select(Some(0));
+            },
+            _ => {},
+        }
+        Ok(LibraryAction::Stay)
+    }
+
+    pub fn render(&mut self, f: &mut Frame, cache: &ArticleCache, bookmarks: &Bookmarks) {
+        let area = popup_area(f.area(), 90, 24);
+        f.render_widget(Clear, area);
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let (title, keys) = match self.shelf {
+            Shelf::Bookmarks => ("Bookmarks", "Tab: saved articles, Enter: open, Delete: remove, Esc: close"),
+            Shelf::Saved => ("Saved articles", "Tab: bookmarks, Enter: open, Esc: close"),
+        };
+        let query_block = Block::default().title(format!("{title} ({keys})")).borders(Borders::ALL);
+        f.render_widget(Paragraph::new(self.query.clone()).block(query_block), layout[0]);
+        let items: Vec<String> = self.entries(cache, bookmarks).into_iter().map(|(_, line)| line).collect();
+        let list_title = match (items.is_empty(), self.shelf) {
+            (true, Shelf::Bookmarks) => "No bookmark: press Ctrl+B on an article to bookmark it",
+            (true, Shelf::Saved) => "No saved article found",
+            (false, _) => "Type to search, Up/Down to select",
+        };
+        let list = List::new(items)
+            .block(Block::default().title(list_title).borders(Borders::ALL))
+            .highlight_style(Style::default().reversed());
+        f.render_stateful_widget(list, layout[1], &mut self.list_state);
+    }
+}
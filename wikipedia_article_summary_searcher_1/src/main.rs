@@ -1,36 +1,56 @@
 /* You can contribute to this solution by giving for example the user the choice to change the language of API in every loop
  */
+mod cache;
+mod history;
+mod languages;
+mod library;
+mod reader;
+mod suggestions;
 
 use crossterm::{
-    event::{read, Event, KeyEventKind, KeyCode},
+    event::{poll, read, Event, KeyEventKind, KeyCode, KeyModifiers},
     execute,
-    terminal::{Clear,ClearType},
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
 use serde::{Deserialize, Serialize};
 
-use reqwest::{blocking::get, StatusCode};
-use std::{error::Error, fmt::Debug, io::{stdout, stdin}, collections::HashMap};
-use csv::Reader;
+use reqwest::{blocking::get, StatusCode, Url};
+use std::{error::Error, fmt::Debug, io::stdout, path::Path, time::Duration};
 
+use cache::{ArticleCache, Bookmarks, BOOKMARKS_FILE, CACHE_FILE};
+use history::{History, Step, Visit};
+use languages::{load_languages, Language, LanguagePicker, Picked};
+use library::{Library, LibraryAction, Shelf};
+use reader::{fetch_article, Reader, ReaderAction};
+use suggestions::Suggestions;
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     widgets::{Block, Borders, Paragraph},
     Terminal,
     widgets::Wrap
 };
 
-#[derive(Debug, Deserialize, Default, Serialize)]
+#[derive(Debug, Deserialize, Default, Serialize, Clone)]
 struct CurrentArticle {
     title: String,
     description: String,
     extract: String,
 }
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 struct App {
     search_string: String,
+    languages: Vec<Language>,
+    language: Language,
+    language_picker: Option<LanguagePicker>,// The popup to choose the language, when it is open
+    suggestions: Suggestions,
+    reader: Option<Reader>,// The whole current article, when it is read
+    history: History,
+    cache: ArticleCache,
+    bookmarks: Bookmarks,
+    library: Option<Library>,// The popup of the bookmarks and the saved articles, when it is open
     current_article: CurrentArticle,
     last_error: Option<String>,//This is used to get the last error and store later in the get_article function
     // If we don't store and rely only on printing errors, they will never be printed as we clear them out before printing
@@ -38,11 +58,80 @@ struct App {
 }
 
 impl App {
+    fn url_without_request(&self) -> String {
+        format!("https://{}.{URL_WITHOUT_LANGUGAGE}", self.language.code)
+    }
+    // Open an article: its summary, and the whole of it too when the reader is open. The article goes in the history,
+    // unless we are moving in it. A summary saved not long ago is used as it is, an older one only when the network
+    // fails. Returns false when the article couldn't be opened.
+    fn open(&mut self, title: &str, record: bool) -> Result<bool, Box<dyn Error>> {
+        self.search_string=title.to_string();
+        self.suggestions.clear();
+        match self.cache.get(&self.language.code, title) {
+            Some(cached) if cached.is_fresh() => {
+                self.current_article=cached.article.clone();
+                self.last_error=None;
+            },
+            cached => {
+                let saved=cached.cloned();
+                self.get_article(self.url_without_request())?;
+                match (&self.last_error, saved) {
+                    (None, _) => {
+                        if let Err(error)=self.cache.insert(&self.language.code, title, &self.current_article, &self.bookmarks) {
+                            self.last_error=Some(format!("Error: the article couldn't be saved: {error}"));
+                        }
+                    },
+                    (Some(error), Some(saved)) => {
+                        self.last_error=Some(format!("{error}. Showing the copy saved {} ago", saved.age()));
+                        self.current_article=saved.article;
+                    },
+                    (Some(_), None) => return Ok(false),
+                }
+            },
+        }
+        if record {
+            self.history.visit(Visit { language: self.language.clone(), title: self.current_article.title.clone() });
+        }
+        if self.reader.is_some() {
+            self.read();
+        }
+        Ok(true)
+    }
+    // The article shown is fetched whole to be read
+    fn read(&mut self) {
+        match fetch_article(&self.language.code, &self.current_article.title) {
+            Ok(article) => {
+                self.reader=Some(Reader::new(article));
+                self.last_error=None;
+            },
+            Err(error) => self.last_error=Some(error),
+        }
+    }
+    fn toggle_bookmark(&mut self) {
+        if let Err(error)=self.bookmarks.toggle(&self.language.code, &self.current_article.title) {
+            self.last_error=Some(format!("Error: the bookmarks couldn't be saved: {error}"));
+        }
+    }
+    // Going back or forward may change the language too. We only move in the history once the article is shown.
+    fn go_to(&mut self, step: Step) -> Result<(), Box<dyn Error>> {
+        let Some(visit)=self.history.peek(step).cloned() else {
+            return Ok(());
+        };
+        let language=std::mem::replace(&mut self.language, visit.language);
+        if self.open(&visit.title, false)? {
+            self.history.step(step);
+        } else {
+            self.language=language;
+        }
+        Ok(())
+    }
     fn get_article(&mut self, url_wihtout_request: String) -> Result<(), Box<dyn Error>> {
-        let url=format!("{url_wihtout_request}/{}", self.search_string);
+        let mut url=Url::parse(&url_wihtout_request)?;
+        // The title is a single segment of the path, even with a '/' like "AC/DC"
+        url.path_segments_mut().map_err(|_| "Error: invalid URL")?.push(&self.search_string.replace(' ', "_"));
         //HTTP errors are also part of the response token. you will have to match the status codes  
         //of the get method in reqwest crate when a response is returned
-        match get(&url) {
+        match get(url) {
             Ok(response) => {
                 match response.status() {
                     StatusCode::OK => {
@@ -79,11 +168,20 @@ impl App {
             }
             Ok(())
         }
-    fn draw_response(&self) -> Result<(), anyhow::Error>{
+    fn draw_response(&mut self) -> Result<(), anyhow::Error>{
             let stdout=stdout();
             let backend=CrosstermBackend::new(&stdout);
             let mut terminal=Terminal::new(backend)?;
             terminal.draw( |f| {
+                // The reader takes the whole screen
+                let breadcrumbs=self.history.breadcrumbs();
+                if let Some(reader)=&mut self.reader {
+                    reader.render(f, &breadcrumbs);
+                    if let Some(library)=&mut self.library {
+                        library.render(f, &self.cache, &self.bookmarks);
+                    }
+                    return;
+                }
                 let layout=Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Percentage(10), Constraint::Percentage(10),
@@ -95,16 +193,21 @@ impl App {
             let article_extract_area=layout[3];
             let error_area=layout[4];
 
-            let search_block=Block::default().title("Searching for").borders(Borders::ALL);
-            let title_block=Block::default().title("Article title").borders(Borders::ALL);
+            let search_title=format!("Searching for in {} (Tab: change language, Ctrl+O: bookmarks, Ctrl+F: saved articles, Ctrl+C: quit)",
+                self.language.name);
+            let search_block=Block::default().title(search_title).borders(Borders::ALL);
+            let title_block=Block::default().title("Article title (Alt+Left/Right: back/forward, Ctrl+B: bookmark)").title_bottom(breadcrumbs)
+            .borders(Borders::ALL);
             let description_block=Block::default().title("Article description").borders(Borders::ALL);
-            let extract_block=Block::default().title("Article extract").borders(Borders::ALL);
+            let extract_title=if self.current_article.title.is_empty() {"Article extract"} else {"Article extract (Ctrl+R: read the whole article)"};
+            let extract_block=Block::default().title(extract_title).borders(Borders::ALL);
             let error_block=Block::default().title("Error").borders(Borders::ALL);
 
             let search_text=Paragraph::new(self.search_string.clone())
             .block(search_block)
             .wrap(Wrap {trim: false}); // add the wrapping in order for the text no to overflow the borders
-            let title_text=Paragraph::new(self.current_article.title.clone())
+            let bookmarked=self.bookmarks.contains(&self.language.code, &self.current_article.title);
+            let title_text=Paragraph::new(if bookmarked {format!("★ {}", self.current_article.title)} else {self.current_article.title.clone()})
             .block(title_block)
             .wrap(Wrap {trim: false});
             let description_text=Paragraph::new(self.current_article.description.clone())
@@ -123,6 +226,13 @@ impl App {
             f.render_widget(description_text, article_description_area);
             f.render_widget(extract_text, article_extract_area);
             f.render_widget(error_text, error_area);
+            self.suggestions.render(f, search_area);
+            if let Some(picker)=&mut self.language_picker {
+                picker.render(f);
+            }
+            if let Some(library)=&mut self.library {
+                library.render(f, &self.cache, &self.bookmarks);
+            }
         })?;
         Ok(())
         
@@ -151,56 +261,145 @@ impl App {
 */
 //Remove the language code to let the choice to the user hereafter 
 const URL_WITHOUT_LANGUGAGE: &str= "wikipedia.org/api/rest_v1/page/summary";
-// Function to load the "wikipedia_languages" file and form the corresponding Hashmap to use it later on
-fn load_languages() -> Result<HashMap<String,String>, Box<dyn Error>> {
-    let mut langs = HashMap::new();
-    let mut reader = Reader::from_path("wikipedia_languages.csv")?;
-    //We skip the first record (headers)
-    for result in reader.records().skip(1) {
-        let record = result?;
-        if let (Some(lang), Some(code)) = (record.get(0), record.get(1)) {
-            langs.insert(lang.to_string(), code.to_string());
-        }
+// How long the loop waits for a key before looking for suggestions
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Area of the given size in the middle of another one, shrunk to fit in it
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width=width.min(area.width);
+    let height=height.min(area.height);
+    Rect::new(area.x+(area.width-width)/2, area.y+(area.height-height)/2, width, height)
+}
+// Raw mode lets the arrow keys reach the picker, it is left with the alternate screen when the guard is dropped,
+// even when the loop ends on an error
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self, Box<dyn Error>> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        Ok(TerminalGuard)
     }
-    Ok(langs)
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let language_map = load_languages()?;
-    let mut app=App::default();
-    let mut input_language = String::new();
-    println!("Please type the language of the API [English]");
-    
-    stdin().read_line(&mut input_language)?;
-    let language = input_language.trim_end().to_lowercase();// We should only trim the end of the string 
-    //to remove the characters '\n' added when you press Enter key
-    let mut url_without_request =format!("https://en.{URL_WITHOUT_LANGUGAGE}");//Default to English
-    if let Some(code) = language_map.get(&language) {
-        url_without_request = format!("https://{}.{URL_WITHOUT_LANGUGAGE}", code);
-        println!("Language found: {}", language);
-    } else {
-        println!("Language not found. Using English as default");
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _=disable_raw_mode();
+        let _=execute!(stdout(), LeaveAlternateScreen);
     }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let languages = load_languages()?;
+    // English until another language is chosen in the picker, which is open at the start
+    let english = languages.iter().find(|language| language.code == "en").cloned()
+        .unwrap_or(Language { name: "english".to_string(), code: "en".to_string() });
+    let mut app=App {
+        language_picker: Some(LanguagePicker::new(&languages, &english)),
+        language: english,
+        languages,
+        cache: ArticleCache::load(Path::new(CACHE_FILE)),
+        bookmarks: Bookmarks::load(Path::new(BOOKMARKS_FILE)),
+        ..App::default()
+    };
+    let _guard=TerminalGuard::enter()?;
+    let mut redraw=true;
     loop {
         //println!("{}", app);
-        let _drawing=app.draw_response();
-        _drawing?;
-
+        // The screen is only drawn again when something changed, as the loop wakes up often for the suggestions
+        if redraw {
+            let _drawing=app.draw_response();
+            _drawing?;
+        }
+        redraw=app.suggestions.update(&app.language.code, &app.search_string);
+        if !poll(POLL_INTERVAL)? {
+            continue;
+        }
+        redraw=true;
         if let Event::Key(key_event)=read()? {
             if key_event.kind==KeyEventKind::Press {
-                match key_event.code {
-                    KeyCode::Backspace => {
-                        app.search_string.pop();
-                    },
-                    KeyCode::Esc => app.search_string.clear(),// I think it would have been better to break the loop here
-                    // But let's respect the choice of the author. Unfortunately, quitting the program is done
-                    // by pressing an ugly Ctrl+C
-                    KeyCode::Enter =>  app.get_article(url_without_request.clone())?,
-                    KeyCode::Char(c) => app.search_string.push(c),
-                    _ => {}
+                // Ctrl+C still quits now that the terminal is in raw mode
+                if key_event.code==KeyCode::Char('c') && key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    break;
+                }
+                let alt=key_event.modifiers.contains(KeyModifiers::ALT);
+                let control=key_event.modifiers.contains(KeyModifiers::CONTROL);
+                let popup=app.language_picker.is_some() || app.library.is_some();
+                if let Some(library)=&mut app.library {
+                    match library.handle_key(key_event.code, &app.cache, &mut app.bookmarks)? {
+                        LibraryAction::Stay => {},
+                        LibraryAction::Close => app.library=None,
+                        // Only the summaries are saved, they are opened out of the reader
+                        LibraryAction::Open(bookmark) => {
+                            app.library=None;
+                            app.reader=None;
+                            let language=app.languages.iter().find(|language| language.code==bookmark.language).cloned()
+                                .unwrap_or(Language { name: bookmark.language.clone(), code: bookmark.language });
+                            app.language=language;
+                            app.open(&bookmark.title, true)?;
+                        },
+                    }
+                } else if control && key_event.code==KeyCode::Char('b') && !popup && !app.current_article.title.is_empty() {
+                    app.toggle_bookmark();
+                } else if control && key_event.code==KeyCode::Char('o') && !popup {
+                    app.library=Some(Library::new(Shelf::Bookmarks));
+                } else if control && key_event.code==KeyCode::Char('f') && !popup {
+                    app.library=Some(Library::new(Shelf::Saved));
+                } else if alt && key_event.code==KeyCode::Left && !popup {
+                    app.go_to(Step::Back)?;
+                } else if alt && key_event.code==KeyCode::Right && !popup {
+                    app.go_to(Step::Forward)?;
+                } else if let Some(reader)=&mut app.reader {
+                    match reader.handle_key(key_event.code) {
+                        ReaderAction::Stay => {},
+                        ReaderAction::Close => app.reader=None,
+                        ReaderAction::Back => app.go_to(Step::Back)?,
+                        ReaderAction::Open(title) => {app.open(&title, true)?;},
+                    }
+                } else if let Some(picker)=&mut app.language_picker {
+                    match picker.handle_key(key_event.code, &app.languages) {
+                        Picked::Nothing => {},
+                        Picked::Cancelled => app.language_picker=None,
+                        Picked::Language(language) => {
+                            app.language_picker=None;
+                            // The current search is run again in the wiki of the new language
+                            let rerun=language!=app.language && !app.search_string.is_empty();
+                            app.language=language;
+                            app.suggestions.clear();
+                            if rerun {
+                                app.open(&app.search_string.clone(), true)?;
+                            }
+                        },
+                    }
+                } else {
+                    match key_event.code {
+                        KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) && !app.current_article.title.is_empty() => app.read(),
+                        KeyCode::Backspace => {
+                            app.search_string.pop();
+                            app.suggestions.query_changed();
+                        },
+                        KeyCode::Esc => {// I think it would have been better to break the loop here
+                            // But let's respect the choice of the author. Quitting the program is done by pressing Ctrl+C
+                            app.search_string.clear();
+                            app.suggestions.clear();
+                        },
+                        // The selected suggestion replaces what was typed
+                        KeyCode::Enter => {
+                            let title=app.suggestions.selected().cloned().unwrap_or(app.search_string.clone());
+                            app.open(&title, true)?;
+                        },
+                        KeyCode::Up => app.suggestions.select_previous(),
+                        KeyCode::Down => app.suggestions.select_next(),
+                        KeyCode::Tab => app.language_picker=Some(LanguagePicker::new(&app.languages, &app.language)),
+                        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.search_string.push(c);
+                            app.suggestions.query_changed();
+                        },
+                        _ => {}
+                    }
                 }
             }
             execute!(stdout(), Clear(ClearType::All))?;
         }
     }
+    Ok(())
 }
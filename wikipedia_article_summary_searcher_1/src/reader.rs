@@ -0,0 +1,587 @@
+// Reader mode: the whole article, fetched as HTML from the REST API and turned into headings and paragraphs. The
+// tables, references and boxes around the text are left out. A table of contents jumps to the sections, and the
+// links to other articles can be selected one after the other and opened.
+use crossterm::event::KeyCode;
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListState, Paragraph},
+    Frame,
+};
+use reqwest::{blocking::get, Url};
+
+use crate::popup_area;
+
+// What is never part of the text of an article
+const SKIPPED_TAGS: [&str; 10] = ["head", "table", "style", "script", "figure", "sup", "math", "noscript", "link", "meta"];
+const SKIPPED_CLASSES: [&str; 7] = ["hatnote", "navbox", "reflist", "mw-references-wrap", "thumb", "metadata", "infobox"];
+// The tags which are never closed, so they have no content to skip
+const VOID_TAGS: [&str; 14] = ["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr"];
+
+// A word of a paragraph, which may be a part of a link to another article
+pub struct Word {
+    pub text: String,
+    pub link: Option<usize>, // Index in the links of the article
+}
+
+pub struct Section {
+    pub level: usize,
+    pub title: String,
+    pub paragraphs: Vec<Vec<Word>>,
+}
+
+pub struct Article {
+    pub title: String,
+    pub sections: Vec<Section>,
+    pub links: Vec<String>, // Titles of the articles linked, in the order of the text
+}
+
+// The HTML entities found in articles, the other ones are kept as they are
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity.strip_prefix("#x").or(entity.strip_prefix("#X"))
+                .map_or_else(|| entity.strip_prefix('#').and_then(|number| number.parse().ok()), |hex| u32::from_str_radix(hex, 16).ok())
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+// Where the tag starting the text ends, a '>' inside quotes doesn't count
+fn tag_end(text: &str) -> usize {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('>', None) => return index,
+            _ => {},
+        }
+    }
+    text.len()
+}
+
+// The value of an attribute of a tag, quoted or not
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    loop {
+        let index = rest.find(&format!("{name}="))?;
+        let value = &rest[index + name.len() + 1..];
+        // "class=" is not the end of "data-class="
+        if rest[..index].chars().last().is_none_or(char::is_whitespace) {
+            return match value.chars().next()? {
+                quote @ ('"' | '\'') => value[1..].split(quote).next(),
+                _ => value.split_whitespace().next(),
+            };
+        }
+        rest = value;
+    }
+}
+
+// The classes of a tag, from its attributes
+fn classes(attributes: &str) -> impl Iterator<Item = &str> {
+    attribute(attributes, "class").unwrap_or("").split_whitespace()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if let Some(byte) = text.get(index + 1..index + 3).filter(|_| bytes[index] == b'%').and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// The title of the article a link leads to, like "./Paris_Hilton#Life". Links to the other sites and to the pages
+// which don't exist yet are not followed.
+fn link_target(attributes: &str) -> Option<String> {
+    if !attribute(attributes, "rel").is_some_and(|rel| rel.contains("mw:WikiLink")) || classes(attributes).any(|class| class == "new") {
+        return None;
+    }
+    let href = decode_entities(attribute(attributes, "href")?);
+    let path = href.strip_prefix("./").or(href.strip_prefix("/wiki/"))?;
+    let title = percent_decode(path.split('#').next()?).replace('_', " ");
+    (!title.is_empty() && !title.contains('?')).then_some(title)
+}
+
+struct Parser {
+    sections: Vec<Section>,
+    links: Vec<String>,
+    paragraph: Vec<(String, Option<usize>)>, // The text as it comes, with the link it is part of
+    link: Option<usize>, // The link the text is in
+    heading: Option<(usize, String)>,
+    skipping: Option<(String, usize)>, // The tag whose content is left out, and how deep it is nested in itself
+}
+
+impl Parser {
+    fn text(&mut self, text: &str) {
+        if self.skipping.is_some() {
+            return;
+        }
+        let text = decode_entities(text);
+        match &mut self.heading {
+            Some((_, heading)) => heading.push_str(&text),
+            None => self.paragraph.push((text, self.link)),
+        }
+    }
+
+    // The spaces of the HTML don't matter, only the words. A word can be cut by tags, like a link followed by "'s".
+    fn end_paragraph(&mut self) {
+        let mut words: Vec<Word> = Vec::new();
+        let mut glued = false; // The last word goes on in the next text
+        for (text, link) in self.paragraph.drain(..) {
+            let continued = glued && !text.starts_with(char::is_whitespace);
+            for (index, piece) in text.split_whitespace().enumerate() {
+                match words.last_mut() {
+                    Some(word) if index == 0 && continued => {
+                        word.text.push_str(piece);
+                        word.link = word.link.or(link);
+                    },
+                    _ => words.push(Word { text: piece.to_string(), link }),
+                }
+            }
+            if !text.is_empty() {
+                glued = !text.ends_with(char::is_whitespace);
+            }
+        }
+        if words.iter().any(|word| word.text != "•") {
+            if let Some(section) = self.sections.last_mut() {
+                section.paragraphs.push(words);
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let name = name.to_lowercase();
+        if let Some((skipped, depth)) = &mut self.skipping {
+            if name == *skipped && !self_closing {
+                if closing {*depth -= 1} else {*depth += 1}
+                if *depth == 0 {
+                    self.skipping = None;
+                }
+            }
+            return;
+        }
+        let skipped_class = classes(attributes).any(|class| SKIPPED_CLASSES.contains(&class));
+        if !closing && !self_closing && (SKIPPED_TAGS.contains(&name.as_str()) || skipped_class) {
+            if !VOID_TAGS.contains(&name.as_str()) {
+                self.skipping = Some((name, 1));
+            }
+            return;
+        }
+        match name.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(2);
+                if !closing {
+                    self.end_paragraph();
+                    self.heading = Some((level, String::new()));
+                } else if let Some((level, title)) = self.heading.take() {
+                    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+                    self.sections.push(Section { level, title, paragraphs: Vec::new() });
+                }
+            },
+            "li" if !closing => {
+                self.end_paragraph();
+                self.paragraph.push(("• ".to_string(), None));
+            },
+            "a" if closing => self.link = None,
+            "a" if self.heading.is_none() => {
+                if let Some(target) = link_target(attributes) {
+                    self.links.push(target);
+                    self.link = Some(self.links.len() - 1);
+                }
+            },
+            "p" | "li" | "dd" | "dt" | "blockquote" | "div" | "ul" | "ol" | "dl" | "section" | "br" => self.end_paragraph(),
+            _ => {},
+        }
+    }
+}
+
+// The lead section has the title of the article
+pub fn parse_html(title: &str, html: &str) -> Article {
+    let mut parser = Parser {
+        sections: vec![Section { level: 1, title: title.to_string(), paragraphs: Vec::new() }],
+        links: Vec::new(),
+        paragraph: Vec::new(),
+        link: None,
+        heading: None,
+        skipping: None,
+    };
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        parser.text(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = tag_end(rest);
+        parser.tag(&rest[1..end]);
+        rest = rest.get(end + 1..).unwrap_or("");
+    }
+    parser.text(rest);
+    parser.end_paragraph();
+    Article { title: title.to_string(), sections: parser.sections, links: parser.links }
+}
+
+pub fn fetch_article(language_code: &str, title: &str) -> Result<Article, String> {
+    let mut url = Url::parse(&format!("https://{language_code}.wikipedia.org/api/rest_v1/page/html"))
+        .map_err(|error| error.to_string())?;
+    // The title is a single segment of the path, even with a '/' like "AC/DC"
+    url.path_segments_mut().map_err(|_| "Error: invalid URL".to_string())?.push(&title.replace(' ', "_"));
+    let response = get(url).map_err(|error| {
+        if error.is_connect() {"No internet connection".to_string()} else {format!("Error: {error}")}
+    })?;
+    if !response.status().is_success() {
+        return Err(format!("Error {}", response.status()));
+    }
+    Ok(parse_html(title, &response.text().map_err(|error| error.to_string())?))
+}
+
+type WrappedLine = Vec<(String, Option<usize>)>;
+
+// Cut the words into lines of at most the width. A word longer than the width is cut.
+fn wrap<'a>(words: impl Iterator<Item = (&'a str, Option<usize>)>, width: usize) -> Vec<WrappedLine> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line: WrappedLine = Vec::new();
+    let mut length = 0;
+    for (word, link) in words {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(vec![(word.drain(..width).collect(), link)]);
+            length = 0;
+        }
+        if length > 0 && length + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+            length = 0;
+        }
+        length += word.len() + (length > 0) as usize;
+        line.push((word.into_iter().collect(), link));
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+pub enum ReaderAction {
+    Stay,
+    Close,
+    Back,
+    Open(String),
+}
+
+pub struct Reader {
+    article: Article,
+    scroll: usize,
+    page_height: usize, // Lines shown at once, known after a drawing
+    section_starts: Vec<usize>, // First line of each section, as wrapped for the last drawing
+    contents: Option<ListState>, // The table of contents, when it is open
+    link_lines: Vec<Option<usize>>, // Line of each link, as wrapped for the last drawing
+    selected_link: Option<usize>,
+    follow_link: bool, // Scroll to the selected link at the next drawing
+}
+
+impl Reader {
+    pub fn new(article: Article) -> Self {
+        Self {
+            article,
+            scroll: 0,
+            page_height: 1,
+            section_starts: Vec::new(),
+            contents: None,
+            link_lines: Vec::new(),
+            selected_link: None,
+            follow_link: false,
+        }
+    }
+
+    // Select the next or the previous link shown, the first one from the top of the page when none is selected yet
+    fn cycle_links(&mut self, forward: bool) {
+        let shown: Vec<(usize, usize)> = self.link_lines.iter().enumerate()
+            .filter_map(|(link, line)| Some((link, (*line)?)))
+            .collect();
+        let (Some(first), Some(last)) = (shown.first(), shown.last()) else {
+            return;
+        };
+        let link = match self.selected_link {
+            None => shown.iter().find(|(_, line)| *line >= self.scroll).unwrap_or(first).0,
+            Some(selected) if forward => shown.iter().find(|(link, _)| *link > selected).unwrap_or(first).0,
+            Some(selected) => shown.iter().rev().find(|(link, _)| *link < selected).unwrap_or(last).0,
+        };
+        self.selected_link = Some(link);
+        self.follow_link = true;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> ReaderAction {
+        if let Some(contents) = &mut self.contents {
+            match code {
+                KeyCode::Esc | KeyCode::Char('t') => self.contents = None,
+                KeyCode::Up => contents.select_previous(),
+                KeyCode::Down => contents.select_next(),
+                KeyCode::Enter => {
+                    let section = contents.selected().unwrap_or(0).min(self.article.sections.len() - 1);
+                    self.scroll = self.section_starts.get(section).copied().unwrap_or(0);
+                    self.contents = None;
+                },
+                _ => {},
+            }
+            return ReaderAction::Stay;
+        }
+        let page = self.page_height.saturating_sub(1).max(1);
+        match code {
+            KeyCode::Esc => return ReaderAction::Close,
+            KeyCode::Backspace => return ReaderAction::Back,
+            KeyCode::Tab => self.cycle_links(true),
+            KeyCode::BackTab => self.cycle_links(false),
+            KeyCode::Enter => {
+                if let Some(link) = self.selected_link {
+                    return ReaderAction::Open(self.article.links[link].clone());
+                }
+            },
+            KeyCode::Char('t') => self.contents = Some(ListState::default().with_selected(Some(self.current_section()))),
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll += 1,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::PageDown => self.scroll += page,
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll = usize::MAX,
+            _ => {},
+        }
+        ReaderAction::Stay
+    }
+
+    // The last section starting above the top of the page
+    fn current_section(&self) -> usize {
+        self.section_starts.iter().rposition(|start| *start <= self.scroll).unwrap_or(0)
+    }
+
+    // The links stand out, the selected one even more
+    fn line<'a>(&self, words: WrappedLine) -> Line<'a> {
+        let mut spans = Vec::new();
+        for (index, (word, link)) in words.into_iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(match link {
+                Some(link) if Some(link) == self.selected_link => Span::raw(word).reversed(),
+                Some(_) => Span::raw(word).cyan().underlined(),
+                None => Span::raw(word),
+            });
+        }
+        Line::from(spans)
+    }
+
+    // The breadcrumbs of the path which led to the article are shown at the bottom
+    pub fn render(&mut self, f: &mut Frame, breadcrumbs: &str) {
+        let area = f.area();
+        let width = area.width.saturating_sub(2) as usize;
+        let mut lines: Vec<Line> = Vec::new();
+        self.section_starts.clear();
+        self.link_lines = vec![None; self.article.links.len()];
+        for section in &self.article.sections {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            self.section_starts.push(lines.len());
+            let heading = wrap(section.title.split_whitespace().map(|word| (word, None)), width).into_iter().map(|words| {
+                let line = self.line(words).bold();
+                if section.level <= 2 {line.underlined()} else {line}
+            });
+            lines.extend(heading);
+            for paragraph in &section.paragraphs {
+                lines.push(Line::default());
+                for words in wrap(paragraph.iter().map(|word| (word.text.as_str(), word.link)), width) {
+                    for link in words.iter().filter_map(|(_, link)| *link) {
+                        self.link_lines[link].get_or_insert(lines.len());
+                    }
+                    lines.push(self.line(words));
+                }
+            }
+        }
+        self.page_height = area.height.saturating_sub(2) as usize;
+        // The selected link is brought into the page
+        if let Some(line) = self.selected_link.and_then(|link| self.link_lines[link]).filter(|_| self.follow_link) {
+            if line < self.scroll || line >= self.scroll + self.page_height {
+                self.scroll = line.saturating_sub(self.page_height / 2);
+            }
+        }
+        self.follow_link = false;
+        self.scroll = self.scroll.min(lines.len().saturating_sub(self.page_height));
+        let sections = &self.article.sections;
+        let section = &sections[self.current_section()];
+        let keys = "t: contents, Tab: links, Enter: open, Backspace: back, Esc: summary";
+        let title = if section.level == 1 {
+            format!("Reader: {} ({keys})", section.title)
+        } else {
+            format!("Reader: {} > {} ({keys})", self.article.title, section.title)
+        };
+        let visible: Vec<Line> = lines.into_iter().skip(self.scroll).take(self.page_height).collect();
+        let block = Block::default().title(title).title_bottom(breadcrumbs.to_string()).borders(Borders::ALL);
+        f.render_widget(Paragraph::new(visible).block(block), area);
+        if let Some(contents) = &mut self.contents {
+            let items: Vec<String> = sections.iter()
+                .map(|section| format!("{}{}", "  ".repeat(section.level.saturating_sub(1)), section.title))
+                .collect();
+            let popup = popup_area(area, 60, items.len() as u16 + 2);
+            f.render_widget(Clear, popup);
+            let list = List::new(items)
+                .block(Block::default().title("Contents (Enter: go, Esc: close)").borders(Borders::ALL))
+                .highlight_style(Style::default().reversed());
+            f.render_stateful_widget(list, popup, contents);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The text of each paragraph of each section, with the titles of the sections
+    fn texts(article: &Article) -> Vec<(String, Vec<String>)> {
+        article.sections.iter()
+            .map(|section| {
+                let paragraphs = section.paragraphs.iter()
+                    .map(|words| words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" "))
+                    .collect();
+                (section.title.clone(), paragraphs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry &lt;3&gt; &quot;hi&quot; &apos;"), "Tom & Jerry <3> \"hi\" '");
+        assert_eq!(decode_entities("caf&#233; &#xE9;&#XE9; a&nbsp;b"), "café éé a b");
+        // Unknown entities and lone ampersands are kept
+        assert_eq!(decode_entities("&copy; AT&T & &#xZZ; &verylongentityname;"), "&copy; AT&T & &#xZZ; &verylongentityname;");
+    }
+
+    #[test]
+    fn percent_decodes_utf8_titles() {
+        assert_eq!(percent_decode("Caf%C3%A9_de_Flore"), "Café_de_Flore");
+        assert_eq!(percent_decode("AC%2FDC"), "AC/DC");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn links_lead_to_existing_articles_only() {
+        assert_eq!(link_target(r#"rel="mw:WikiLink" href="./Paris_Hilton#Life""#), Some("Paris Hilton".to_string()));
+        assert_eq!(link_target(r#"href="./AC%2FDC" rel="mw:WikiLink""#), Some("AC/DC".to_string()));
+        assert_eq!(link_target(r#"rel="mw:WikiLink" href="/wiki/Tom_&amp;_Jerry""#), Some("Tom & Jerry".to_string()));
+        // Red links, other sites and pages which aren't articles
+        assert_eq!(link_target(r#"rel="mw:WikiLink" class="new" href="./Nothing""#), None);
+        assert_eq!(link_target(r#"rel="mw:ExtLink" href="https://example.com""#), None);
+        assert_eq!(link_target(r#"rel="mw:WikiLink" href="./Special:Search?q=x""#), None);
+        assert_eq!(link_target(r#"rel="mw:WikiLink" data-href="./Paris""#), None);
+    }
+
+    #[test]
+    fn sections_split_the_article() {
+        let html = "<html><head><title>Paris</title><style>p {}</style></head><body>
+            <section><p>Paris is the <b>capital</b>\n of France.</p><p>Second   paragraph.</p></section>
+            <section><h2 id=\"History\">History <span>of Paris</span></h2><p>Old.</p>
+            <h3>Middle Ages</h3><ul><li>One</li><li>Two</li></ul></section></body></html>";
+        let article = parse_html("Paris", html);
+        assert_eq!(texts(&article), vec![
+            ("Paris".to_string(), vec!["Paris is the capital of France.".to_string(), "Second paragraph.".to_string()]),
+            ("History of Paris".to_string(), vec!["Old.".to_string()]),
+            ("Middle Ages".to_string(), vec!["• One".to_string(), "• Two".to_string()]),
+        ]);
+        assert_eq!(article.sections.iter().map(|section| section.level).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn tables_references_and_boxes_are_left_out() {
+        let html = r##"<div class="hatnote">See also Paris (disambiguation)</div>
+            <p>Paris<sup class="reference"><a href="#cite-1">[1]</a></sup> is big.</p>
+            <table class="infobox"><tr><td>Population</td></tr></table>
+            <!-- a comment <p>hidden</p> -->
+            <p>The end.</p>"##;
+        assert_eq!(texts(&parse_html("Paris", html))[0].1, vec!["Paris is big.", "The end."]);
+    }
+
+    #[test]
+    fn nested_tags_of_a_skipped_one_are_counted() {
+        let html = r#"<div class="navbox"><div>Inner <div>deeper</div></div> still in the box</div><p>After the box.</p>"#;
+        assert_eq!(texts(&parse_html("Paris", html))[0].1, vec!["After the box."]);
+    }
+
+    #[test]
+    fn void_tags_with_a_skipped_class_skip_nothing() {
+        let html = r#"<p>Before <img class="thumb" src="a.png"> the picture.</p><hr class="metadata"><br class="navbox">
+            <input class="infobox"><p>After it.</p>"#;
+        assert_eq!(texts(&parse_html("Paris", html))[0].1, vec!["Before the picture.", "After it."]);
+    }
+
+    #[test]
+    fn links_are_numbered_in_the_order_of_the_text() {
+        let html = r#"<p>See <a rel="mw:WikiLink" href="./Seine" title="a > b">the Seine</a>'s banks and
+            <a rel="mw:WikiLink" href="./Louvre">Louvre</a>.</p><h2><a rel="mw:WikiLink" href="./Ignored">Title</a></h2>"#;
+        let article = parse_html("Paris", html);
+        assert_eq!(article.links, vec!["Seine", "Louvre"]);
+        let words: Vec<(&str, Option<usize>)> = article.sections[0].paragraphs[0].iter()
+            .map(|word| (word.text.as_str(), word.link))
+            .collect();
+        // The word cut by the end of the link is glued back, and stays a part of it
+        assert_eq!(words, vec![("See", None), ("the", Some(0)), ("Seine's", Some(0)), ("banks", None), ("and", None),
+            ("Louvre.", Some(1))]);
+        assert_eq!(article.sections[1].title, "Title");
+    }
+
+    #[test]
+    fn wrap_fills_the_lines_and_cuts_long_words() {
+        let words = ["one", "two", "three", "abcdefghij"].map(|word| (word, None));
+        let lines: Vec<Vec<String>> = wrap(words.into_iter(), 8).into_iter()
+            .map(|line| line.into_iter().map(|(word, _)| word).collect())
+            .collect();
+        assert_eq!(lines, vec![vec!["one", "two"], vec!["three"], vec!["abcdefgh"], vec!["ij"]]);
+        let links: Vec<WrappedLine> = wrap([("a", Some(2)), ("b", None)].into_iter(), 0);
+        assert_eq!(links, vec![vec![("a".to_string(), Some(2))], vec![("b".to_string(), None)]]);
+    }
+}
@@ -0,0 +1,303 @@
+// Titles suggested while typing, from the opensearch API of the wiki. They are only asked once the typing pauses, and
+// a background thread sends the requests so that the UI never waits for the network.
+use ratatui::{
+    layout::Rect,
+    style::{Style, Stylize},
+    widgets::{Block, Borders, Clear, List, ListState},
+    Frame,
+};
+use reqwest::{blocking::get, Url};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Time without typing before the suggestions are asked
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const LIMIT: &str = "10";
+// The opensearch API of a wiki, "{language}" being replaced by its code. Another one can be given, like a mock server.
+pub const OPENSEARCH_URL: &str = "https://{language}.wikipedia.org/w/api.php";
+
+struct Request {
+    generation: u64,
+    api_url: String,
+    query: String,
+}
+
+struct Response {
+    generation: u64,
+    result: Result<Vec<String>, String>,
+}
+
+// The API answers [query, titles, descriptions, links]
+fn fetch(request: &Request) -> Result<Vec<String>, String> {
+    let url = Url::parse_with_params(&request.api_url, &[
+        ("action", "opensearch"),
+        ("search", request.query.as_str()),
+        ("limit", LIMIT),
+        ("namespace", "0"),
+        ("format", "json"),
+    ]).map_err(|error| error.to_string())?;
+    let response = get(url).map_err(|error| {
+        if error.is_connect() {"No internet connection".to_string()} else {format!("Error: {error}")}
+    })?;
+    if !response.status().is_success() {
+        return Err(format!("Error {}", response.status()));
+    }
+    let (_, titles, _, _): (String, Vec<String>, Vec<String>, Vec<String>) = serde_json::from_str(&response.text()
+        .map_err(|error| error.to_string())?)
+        .map_err(|_| "Error! Received an invalid json format".to_string())?;
+    Ok(titles)
+}
+
+// Only the latest query matters: the ones typed while a request was running are skipped
+fn spawn_worker(requests: Receiver<Request>, responses: Sender<Response>) {
+    thread::spawn(move || {
+        while let Ok(mut request) = requests.recv() {
+            while let Ok(newer) = requests.try_recv() {
+                request = newer;
+            }
+            let result = fetch(&request);
+            if responses.send(Response { generation: request.generation, result }).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+pub struct Suggestions {
+    api_url: String, // Opensearch API, with the language to fill in
+    requests: Sender<Request>,
+    responses: Receiver<Response>,
+    typed_at: Option<Instant>, // When the query last changed, until it is requested
+    generation: u64, // Answers to older requests are dropped
+    titles: Vec<String>,
+    error: Option<String>,
+    list_state: ListState,
+}
+
+impl Default for Suggestions {
+    fn default() -> Self {
+        Self::new(OPENSEARCH_URL)
+    }
+}
+
+impl Suggestions {
+    pub fn new(api_url: &str) -> Self {
+        let (requests, worker_requests) = channel();
+        let (worker_responses, responses) = channel();
+        spawn_worker(worker_requests, worker_responses);
+        Self {
+            api_url: api_url.to_string(),
+            requests,
+            responses,
+            typed_at: None,
+            generation: 0,
+            titles: Vec::new(),
+            error: None,
+            list_state: ListState::default(),
+        }
+    }
+
+    pub fn query_changed(&mut self) {
+        self.typed_at = Some(Instant::now());
+    }
+
+    // Close the dropdown, the answer of a running request included
+    pub fn clear(&mut self) {
+        self.typed_at = None;
+        self.generation += 1;
+        self.titles.clear();
+        self.error = None;
+        self.list_state.select(None);
+    }
+
+    // Ask the suggestions once the typing paused and take the answers of the background thread. Returns true when
+    // the dropdown changed.
+    pub fn update(&mut self, language_code: &str, query: &str) -> bool {
+        let mut changed = false;
+        if self.typed_at.is_some_and(|typed_at| typed_at.elapsed() >= DEBOUNCE) {
+            if query.trim().is_empty() {
+                self.clear();
+                changed = true;
+            } else {
+                self.typed_at = None;
+                self.generation += 1;
+                let api_url = self.api_url.replace("{language}", language_code);
+                let request = Request { generation: self.generation, api_url, query: query.to_string() };
+                // The worker only stops with the app
+                let _ = self.requests.send(request);
+            }
+        }
+        while let Ok(response) = self.responses.try_recv() {
+            if response.generation != self.generation {
+                continue;
+            }
+            match response.result {
+                Ok(titles) => {
+                    self.titles = titles;
+                    self.error = None;
+                },
+                Err(error) => {
+                    self.titles.clear();
+                    self.error = Some(error);
+                },
+            }
+            self.list_state.select(None);
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(last) = self.titles.len().checked_sub(1) {
+            self.list_state.select(Some(self.list_state.selected().map_or(0, |index| (index + 1).min(last))));
+        }
+    }
+
+    // Going up from the first suggestion gets back to the typed text
+    pub fn select_previous(&mut self) {
+        self.list_state.select(self.list_state.selected().and_then(|index| index.checked_sub(1)));
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.list_state.selected().and_then(|index| self.titles.get(index))
+    }
+
+    // The dropdown hangs below the search box, over the article
+    pub fn render(&mut self, f: &mut Frame, search_area: Rect) {
+        if self.titles.is_empty() && self.error.is_none() {
+            return;
+        }
+        let top = search_area.bottom().min(f.area().bottom());
+        let height = (self.titles.len().max(1) as u16 + 2).min(f.area().bottom() - top);
+        let area = Rect::new(search_area.x, top, search_area.width, height);
+        f.render_widget(Clear, area);
+        let title = match &self.error {
+            Some(error) => format!("No suggestion: {error}"),
+            None => "Suggestions (Up/Down: select, Enter: open)".to_string(),
+        };
+        let list = List::new(self.titles.clone())
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().reversed());
+        f.render_stateful_widget(list, area, &mut self.list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::{BufRead, BufReader, Write}, net::TcpListener};
+
+    // Local server answering each request once it is released, with the titles of the query, and reporting the
+    // requested paths
+    fn mock_server(release: Receiver<()>, paths: Sender<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/{{language}}/api.php", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers, the requests have no body
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let query = Url::parse(&format!("http://localhost{path}")).unwrap().query_pairs()
+                    .find(|(key, _)| key == "search").map(|(_, value)| value.into_owned()).unwrap_or_default();
+                paths.send(path).unwrap();
+                if release.recv().is_err() {
+                    return;
+                }
+                let body = serde_json::to_string(&(&query, [format!("{query} title")], [""], [""])).unwrap();
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+                    .unwrap();
+            }
+        });
+        url
+    }
+
+    // Type the query and let the debounce delay go by
+    fn type_query(suggestions: &mut Suggestions, query: &str) {
+        suggestions.query_changed();
+        suggestions.typed_at = suggestions.typed_at.map(|typed_at| typed_at - DEBOUNCE);
+        suggestions.update("fr", query);
+    }
+
+    // Take the answers until the dropdown changes, and return what it shows
+    fn wait_for_titles(suggestions: &mut Suggestions, query: &str) -> Vec<String> {
+        for _ in 0..200 {
+            if suggestions.update("fr", query) {
+                return suggestions.titles.clone();
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("no answer for {query}");
+    }
+
+    #[test]
+    fn suggestions_wait_for_the_typing_to_pause() {
+        let (release, released) = channel();
+        let (sender, paths) = channel();
+        let mut suggestions = Suggestions::new(&mock_server(released, sender));
+        suggestions.query_changed();
+        assert!(!suggestions.update("fr", "par"));
+        thread::sleep(Duration::from_millis(50));
+        assert!(paths.try_recv().is_err());
+        type_query(&mut suggestions, "paris");
+        let path = paths.recv().unwrap();
+        assert!(path.starts_with("/fr/api.php?action=opensearch&search=paris&limit=10"), "{path}");
+        release.send(()).unwrap();
+        assert_eq!(wait_for_titles(&mut suggestions, "paris"), ["paris title"]);
+    }
+
+    #[test]
+    fn answers_to_older_queries_are_dropped() {
+        let (release, released) = channel();
+        let (sender, paths) = channel();
+        let mut suggestions = Suggestions::new(&mock_server(released, sender));
+        type_query(&mut suggestions, "par");
+        // The first request runs while the query changes
+        assert!(paths.recv().unwrap().contains("search=par&"));
+        type_query(&mut suggestions, "pari");
+        release.send(()).unwrap();
+        assert!(paths.recv().unwrap().contains("search=pari&"));
+        release.send(()).unwrap();
+        assert_eq!(wait_for_titles(&mut suggestions, "pari"), ["pari title"]);
+    }
+
+    #[test]
+    fn clear_drops_the_answer_of_a_running_request() {
+        let (release, released) = channel();
+        let (sender, paths) = channel();
+        let mut suggestions = Suggestions::new(&mock_server(released, sender));
+        type_query(&mut suggestions, "par");
+        paths.recv().unwrap();
+        suggestions.clear();
+        type_query(&mut suggestions, "lyon");
+        release.send(()).unwrap();
+        paths.recv().unwrap();
+        release.send(()).unwrap();
+        // The answer to "par" came first, it never shows up
+        assert_eq!(wait_for_titles(&mut suggestions, "lyon"), ["lyon title"]);
+    }
+
+    #[test]
+    fn selection_stays_in_the_list() {
+        let mut suggestions = Suggestions::new(OPENSEARCH_URL);
+        suggestions.select_next();
+        assert_eq!(suggestions.selected(), None);
+        suggestions.titles = vec!["Paris".to_string(), "Paris Hilton".to_string()];
+        suggestions.select_next();
+        suggestions.select_next();
+        suggestions.select_next();
+        assert_eq!(suggestions.selected().map(String::as_str), Some("Paris Hilton"));
+        suggestions.select_previous();
+        suggestions.select_previous();
+        assert_eq!(suggestions.selected(), None);
+    }
+}